@@ -1,26 +1,100 @@
 #![allow(dead_code)]
 
 use super::color::Color;
+use super::coord::Coord;
 use super::piece::Piece;
 
-pub const BOARD_WIDTH: usize = 10;
-pub const BOARD_HEIGHT: usize = 20;
+pub const DEFAULT_WIDTH: usize = 10;
+pub const DEFAULT_HEIGHT: usize = 20;
+/// Hidden rows stored above the visible playfield.
+pub const DEFAULT_BUFFER: usize = 20;
 
-pub const INSERTION_POINT: i8 = BOARD_HEIGHT as i8 - 1;
+/// Smallest board every piece can spawn and rotate in.
+pub const MIN_SIZE: usize = 4;
+/// Largest board whose coordinates fit in a `Coord`.
+pub const MAX_SIZE: usize = 100;
 
 #[derive(Debug, Copy, Clone)]
-pub struct BoardSquare(Color);
+pub struct BoardSquare(pub Color);
 
+/// What a call to `Board::clear_lines` removed.
+#[derive(Debug, Clone, Default)]
+pub struct ClearResult {
+    /// Indices of the cleared rows before the clear, lowest first.
+    pub rows: Vec<usize>,
+    /// The removed cells as `(x, y, square)`, in coordinates from before
+    /// the clear.
+    pub cells: Vec<(usize, usize, BoardSquare)>,
+    /// Whether the board is empty after the clear.
+    pub empty: bool,
+}
+
+impl ClearResult {
+    pub fn lines(&self) -> u8 {
+        self.rows.len() as u8
+    }
+    
+    /// Rows were cleared and nothing is left.
+    pub fn is_perfect_clear(&self) -> bool {
+        !self.rows.is_empty() && self.empty
+    }
+}
+
+/// The playfield: `height` visible rows with `buffer` hidden rows, the
+/// vanish zone, stored above them.
 pub struct Board {
-    data: [[Option<BoardSquare>; BOARD_WIDTH]; BOARD_HEIGHT],
+    width: usize,
+    height: usize,
+    buffer: usize,
+    data: Vec<Vec<Option<BoardSquare>>>,
 }
 
 impl Board {
-    pub fn new() -> Board {
+    pub fn new(width: usize, height: usize) -> Board {
+        Board::with_buffer(width, height, DEFAULT_BUFFER)
+    }
+    
+    pub fn with_buffer(width: usize, height: usize, buffer: usize) -> Board {
+        if width < MIN_SIZE || height < MIN_SIZE || width > MAX_SIZE || height + buffer > MAX_SIZE {
+            panic!(
+                "Board must be between {0}x{0} and {1}x{1}, not {2}x{3} plus {4} hidden rows.",
+                MIN_SIZE, MAX_SIZE, width, height, buffer
+            );
+        }
+        
         Board {
-            data: [[None; BOARD_WIDTH]; BOARD_HEIGHT],
+            width,
+            height,
+            buffer,
+            data: vec![vec![None; width]; height + buffer],
         }
     }
+    
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    
+    /// Number of visible rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    
+    /// Number of hidden rows above the visible ones.
+    pub fn buffer(&self) -> usize {
+        self.buffer
+    }
+    
+    /// Number of stored rows, visible and hidden.
+    pub fn total_height(&self) -> usize {
+        self.height + self.buffer
+    }
+    
+    /// Where new pieces appear: the middle column of the first hidden row,
+    /// or of the top visible row if there are no hidden rows.
+    pub fn insertion_point(&self) -> Coord {
+        let row = if self.buffer > 0 { self.height } else { self.height - 1 };
+        Coord((self.width as i8 - 1) / 2, row as i8)
+    }
 
     fn set(&mut self, x: usize, y: usize, board_square: BoardSquare) {
         self.data[y][x] = Some(board_square);
@@ -42,32 +116,56 @@ impl Board {
         }
     }
     
-    pub fn clear_lines(&mut self) -> u8 {
-        let mut board = &mut self.data;
-        let mut found = 0;
+    pub fn clear_lines(&mut self) -> ClearResult {
+        let height = self.data.len();
+        
+        let rows = (0..height)
+            .filter( |&y| self.data[y].iter().all(Option::is_some) )
+            .collect::<Vec<usize>>();
+        let cells = rows
+            .iter()
+            .flat_map( |&y| self.data[y].iter().enumerate().map(move |(x, square)| (x, y, square.unwrap())) )
+            .collect();
         
         // Collapse full lines
-        for index in 0..board.len() {
-            if board[index].iter().all(Option::is_some) {
-                found += 1;
-                continue;
-            }
-            
-            if found > 0 {
-                board[index - found] = board[index];
-            }
-        }
+        self.data.retain( |row| !row.iter().all(Option::is_some) );
         
         // Fill in top with None
-        for index in (board.len() - found)..board.len() {
-            board[index] = [None::<BoardSquare>; BOARD_WIDTH];
+        let width = self.width;
+        self.data.resize(height, vec![None; width]);
+        
+        ClearResult {
+            rows,
+            cells,
+            empty: self.is_empty(),
+        }
+    }
+    
+    /// Pushes the stack up one row and fills the bottom row, leaving a hole
+    /// in column `hole`. Returns false if blocks were pushed off the top.
+    pub fn push_garbage(&mut self, hole: usize, color: Color) -> bool {
+        if hole >= self.width {
+            panic!("Garbage hole must be in a column below {}, not {}.", self.width, hole);
         }
         
-        found as u8
+        let overflow = self.data
+            .pop()
+            .is_some_and( |row| row.iter().any(Option::is_some) );
+        
+        let row = (0..self.width)
+            .map( |x| if x == hole { None } else { Some(BoardSquare(color)) } )
+            .collect();
+        self.data.insert(0, row);
+        
+        !overflow
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.data.iter().all( |row| row.iter().all(Option::is_none) )
     }
     
-    pub fn iter_with_index<'a>(&'a self) -> IterWithIndex<'a> {
-        IterWithIndex::over(&self)
+    pub fn iter_with_index(&self) -> IterWithIndex<'_> {
+        IterWithIndex::over(self)
     }
 }
 
@@ -82,7 +180,7 @@ impl<'a> IterWithIndex<'a> {
         IterWithIndex {
             x: 0,
             y: 0,
-            board: board,
+            board,
         }
     }
 }
@@ -91,7 +189,7 @@ impl<'a> Iterator for IterWithIndex<'a> {
     type Item = (usize, usize, Option<BoardSquare>);
     
     fn next(&mut self) -> Option<(usize, usize, Option<BoardSquare>)> {
-        if self.y >= BOARD_HEIGHT { return None; }
+        if self.y >= self.board.total_height() { return None; }
         
         let retval = Some(
             (
@@ -101,7 +199,7 @@ impl<'a> Iterator for IterWithIndex<'a> {
             )
         );
         
-        if self.x < BOARD_WIDTH-1 {
+        if self.x < self.board.width - 1 {
             self.x += 1
         } else {
             self.x = 0;
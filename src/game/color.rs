@@ -1,6 +1,6 @@
 #![allow(dead_code)]
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Color(pub u8, pub u8, pub u8);
 
 pub mod named {
@@ -12,4 +12,5 @@ pub mod named {
     pub const PURPLE: Color = Color(127,   0, 255);
     pub const RED:    Color = Color(255,   0,   0);
     pub const ORANGE: Color = Color(255, 127,   0);
+    pub const GRAY:   Color = Color(127, 127, 127);
 }
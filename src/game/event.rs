@@ -0,0 +1,25 @@
+use super::Movement;
+use super::board::ClearResult;
+use super::piece::{Kind, Piece};
+use super::score::ScoreEvent;
+
+/// Something that happened in a game, sent to every subscriber in the order
+/// it happened.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A new piece entered the board from the preview.
+    PieceSpawned(Piece),
+    /// The cursor moved or rotated, by the player or by gravity. Carries the
+    /// cursor after the move; after a hard drop, where it landed.
+    PieceMoved { movement: Movement, piece: Piece },
+    /// A piece locked into the stack, and what it scored.
+    PieceLocked { piece: Piece, score: ScoreEvent },
+    /// Rows were removed by the last lock.
+    LinesCleared(ClearResult),
+    /// The level went up to the one given.
+    LevelUp(u32),
+    /// The cursor was put in the hold slot and replaced by `cursor`.
+    Hold { held: Kind, cursor: Piece },
+    /// The game ended with a block out, lock out or garbage overflow.
+    GameOver { score: u32, lines: u32, seed: Option<u64> },
+}
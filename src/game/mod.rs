@@ -1,15 +1,38 @@
 #![allow(dead_code)]
 
 pub mod color;
-mod piece;
-mod board;
-mod coord;
+pub mod event;
+pub mod randomizer;
+pub mod score;
+pub mod piece;
+pub mod board;
+pub mod coord;
+
+pub use self::board::Board;
+pub use self::coord::Coord;
+pub use self::piece::Piece;
+
+use ::std::collections::VecDeque;
+use ::std::mem;
+use ::std::sync::mpsc;
+use ::std::time::Duration;
+use ::rand;
+use self::rand::{SeedableRng, XorShiftRng};
+use self::event::Event;
+use self::randomizer::Randomizer;
+use self::color::named;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Movement {
     MoveLeft,
     MoveRight,
+    /// One row down, as gravity moves it. Scores nothing.
     MoveDown,
+    /// One row down on behalf of the player, for a point per row.
+    SoftDrop,
+    /// Straight down onto the projection, for two points per row, locking
+    /// the piece immediately.
+    HardDrop,
     
     RotLeft,
     RotRight,
@@ -17,6 +40,43 @@ pub enum Movement {
 
 use self::Movement::*;
 
+pub const DEFAULT_PREVIEW_LENGTH: usize = 5;
+pub const DEFAULT_LOCK_DELAY_MS: u64 = 500;
+pub const LOCK_RESET_LIMIT: u8 = 15;
+pub const DEFAULT_COUNTDOWN_MS: u64 = 3000;
+pub const DEFAULT_LINE_CLEAR_DELAY_MS: u64 = 300;
+
+/// Index of the final SRS kick test, which always upgrades a mini T-spin.
+const LAST_KICK: usize = 4;
+
+/// Milliseconds per row of gravity, indexed by level starting at 1. Levels
+/// past the end of the table keep the last speed.
+pub const DEFAULT_SPEED_CURVE_MS: [u64;15] = [
+    1000, 793, 618, 473, 355, 262, 190, 135, 94, 64, 43, 28, 18, 11, 7,
+];
+
+/// The phases of a game. A game counts down before the first piece
+/// spawns, pauses briefly after clearing lines, and can be paused from any
+/// state but `Over`.
+///
+/// ```text
+/// Countdown ---------------------> Playing <--> LineClearAnimation
+///     |                            |    |               |
+///     +--> Paused (returns to the  |    +----> Over <---+
+///          state it was paused in) |
+///                                  +--> Paused
+/// ```
+///
+/// Restarting returns to `Countdown` from any state.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GameState {
+    Countdown,
+    Playing,
+    Paused,
+    LineClearAnimation,
+    Over,
+}
+
 #[derive(Debug)]
 enum Selection {
     Cursor,
@@ -24,21 +84,396 @@ enum Selection {
 }
 
 pub struct Game {
-    pub score: u32,
+    scoring: score::Scoring,
     board: board::Board,
     cursor: Option<piece::Piece>,
     projection: Option<piece::Piece>,
-    prev_cursor_x: usize,
+    hold: Option<piece::Kind>,
+    hold_available: bool,
+    randomizer: Box<dyn Randomizer>,
+    seed: Option<u64>,
+    preview: VecDeque<piece::Kind>,
+    preview_length: usize,
+    speed_curve: Vec<Duration>,
+    gravity_timer: Duration,
+    lock_delay: Duration,
+    lock_timer: Option<Duration>,
+    lock_resets: u8,
+    lowest_row: i8,
+    state: GameState,
+    paused_state: GameState,
+    state_timer: Duration,
+    countdown: Duration,
+    line_clear_delay: Duration,
+    last_event: Option<score::ScoreEvent>,
+    last_clear: Option<board::ClearResult>,
+    subscribers: Vec<mpsc::Sender<Event>>,
+    last_movement: Option<Movement>,
+    last_kick: usize,
+    spin: score::Spin,
 }
 
 impl Game {
     pub fn new() -> Game {
-        Game {
-            score: 0,
-            board: board::Board::new(),
+        Game::with_seed(rand::random())
+    }
+    
+    /// A game whose entire piece sequence is determined by `seed`.
+    pub fn with_seed(seed: u64) -> Game {
+        let mut game = Game::with_randomizer(Game::seeded_randomizer(seed));
+        game.seed = Some(seed);
+        game
+    }
+    
+    fn seeded_randomizer(seed: u64) -> Box<dyn Randomizer> {
+        let (low, high) = (seed as u32, (seed >> 32) as u32);
+        let rng = XorShiftRng::from_seed([low, high, !low, !high]);
+        Box::new(randomizer::Bag::new(rng))
+    }
+    
+    pub fn with_randomizer(randomizer: Box<dyn Randomizer>) -> Game {
+        let mut game = Game {
+            scoring: score::Scoring::new(),
+            board: board::Board::new(board::DEFAULT_WIDTH, board::DEFAULT_HEIGHT),
             cursor: None,
             projection: None,
-            prev_cursor_x: 0,
+            hold: None,
+            hold_available: true,
+            randomizer,
+            seed: None,
+            preview: VecDeque::new(),
+            preview_length: DEFAULT_PREVIEW_LENGTH,
+            speed_curve: DEFAULT_SPEED_CURVE_MS
+                .iter()
+                .map( |&ms| Duration::from_millis(ms) )
+                .collect(),
+            gravity_timer: Duration::from_millis(0),
+            lock_delay: Duration::from_millis(DEFAULT_LOCK_DELAY_MS),
+            lock_timer: None,
+            lock_resets: 0,
+            lowest_row: 0,
+            state: GameState::Countdown,
+            paused_state: GameState::Countdown,
+            state_timer: Duration::from_millis(DEFAULT_COUNTDOWN_MS),
+            countdown: Duration::from_millis(DEFAULT_COUNTDOWN_MS),
+            line_clear_delay: Duration::from_millis(DEFAULT_LINE_CLEAR_DELAY_MS),
+            last_event: None,
+            last_clear: None,
+            subscribers: Vec::new(),
+            last_movement: None,
+            last_kick: 0,
+            spin: score::Spin::None,
+        };
+        game.fill_preview();
+        game
+    }
+    
+    /// Clears the board and starts over with the same settings, beginning
+    /// with the countdown. Seeded games continue from a fresh random seed.
+    pub fn restart(&mut self) {
+        if self.seed.is_some() {
+            let seed = rand::random();
+            self.randomizer = Game::seeded_randomizer(seed);
+            self.seed = Some(seed);
+        }
+        
+        self.scoring = score::Scoring::new();
+        self.board = board::Board::with_buffer(
+            self.board.width(),
+            self.board.height(),
+            self.board.buffer(),
+        );
+        self.cursor = None;
+        self.projection = None;
+        self.hold = None;
+        self.hold_available = true;
+        self.lock_timer = None;
+        self.spin = score::Spin::None;
+        self.last_event = None;
+        self.last_clear = None;
+        self.preview.clear();
+        self.fill_preview();
+        self.state = GameState::Countdown;
+        self.state_timer = self.countdown;
+    }
+    
+    /// Receives every `Event` from now on, including after restarts.
+    /// Dropping the receiver unsubscribes.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<Event> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+    
+    fn emit(&mut self, event: Event) {
+        if self.subscribers.is_empty() { return; }
+        self.subscribers.retain( |subscriber| subscriber.send(event.clone()).is_ok() );
+    }
+    
+    fn end_game(&mut self) {
+        self.state = GameState::Over;
+        let event = Event::GameOver {
+            score: self.scoring.score(),
+            lines: self.scoring.lines(),
+            seed: self.seed,
+        };
+        self.emit(event);
+    }
+    
+    /// Freezes the game until `resume` is called. Fails if the game is
+    /// already paused or over.
+    pub fn pause(&mut self) -> Result<(),()> {
+        match self.state {
+            GameState::Paused | GameState::Over => Err(()),
+            state => {
+                self.paused_state = state;
+                self.state = GameState::Paused;
+                Ok(())
+            }
+        }
+    }
+    
+    /// Returns to the state the game was paused in.
+    pub fn resume(&mut self) -> Result<(),()> {
+        if self.state != GameState::Paused { return Err(()); }
+        self.state = self.paused_state;
+        Ok(())
+    }
+    
+    /// Replaces the board with an empty one of the given size. Meant to be
+    /// called before the first piece spawns.
+    pub fn set_board_size(&mut self, width: usize, height: usize) {
+        let buffer = self.board.buffer();
+        self.board = board::Board::with_buffer(width, height, buffer);
+        self.cursor = None;
+        self.projection = None;
+    }
+    
+    /// Sets how many hidden rows sit above the visible playfield. Like
+    /// `set_board_size`, this empties the board.
+    pub fn set_buffer_height(&mut self, buffer: usize) {
+        let (width, height) = (self.board.width(), self.board.height());
+        self.board = board::Board::with_buffer(width, height, buffer);
+        self.cursor = None;
+        self.projection = None;
+    }
+    
+    pub fn board_width(&self) -> usize {
+        self.board.width()
+    }
+    
+    /// Number of visible rows.
+    pub fn board_height(&self) -> usize {
+        self.board.height()
+    }
+    
+    pub fn buffer_height(&self) -> usize {
+        self.board.buffer()
+    }
+    
+    /// Sets how many upcoming pieces `get_preview` reveals. Pieces already
+    /// drawn stay queued, so the sequence does not depend on this length.
+    pub fn set_preview_length(&mut self, length: usize) {
+        self.preview_length = length;
+        self.fill_preview();
+    }
+    
+    /// Sets the gravity interval for each level, starting at level 1. An
+    /// interval of zero drops the piece as far as it goes on every tick.
+    pub fn set_speed_curve(&mut self, speed_curve: Vec<Duration>) {
+        if speed_curve.is_empty() {
+            panic!("Tried to set an empty speed curve.");
+        }
+        self.speed_curve = speed_curve;
+    }
+    
+    pub fn gravity_interval(&self) -> Duration {
+        let index = (self.scoring.level() as usize - 1).min(self.speed_curve.len() - 1);
+        self.speed_curve[index]
+    }
+    
+    pub fn set_lock_delay(&mut self, lock_delay: Duration) {
+        self.lock_delay = lock_delay;
+    }
+    
+    /// How long to count down before the first piece spawns. Takes effect
+    /// on the next restart.
+    pub fn set_countdown(&mut self, countdown: Duration) {
+        self.countdown = countdown;
+    }
+    
+    /// How long to wait after clearing lines before the next piece spawns.
+    /// Zero spawns it immediately.
+    pub fn set_line_clear_delay(&mut self, line_clear_delay: Duration) {
+        self.line_clear_delay = line_clear_delay;
+    }
+    
+    /// Advances the game clock. Counts down the countdown and line clear
+    /// delay, lets gravity pull the cursor down, and locks it once it has
+    /// rested on the stack for the lock delay. Returns whether anything
+    /// changed.
+    pub fn advance(&mut self, elapsed: Duration) -> bool {
+        match self.state {
+            GameState::Countdown => {
+                let seconds = self.get_countdown();
+                if self.count_down(elapsed) {
+                    self.refill_cursor();
+                    true
+                } else {
+                    seconds != self.get_countdown()
+                }
+            },
+            GameState::LineClearAnimation => {
+                // The animation moves on with every tick.
+                if self.count_down(elapsed) {
+                    self.refill_cursor();
+                }
+                true
+            },
+            GameState::Playing => self.advance_cursor(elapsed),
+            GameState::Paused | GameState::Over => false,
+        }
+    }
+    
+    /// Runs down the state timer, returning whether it has expired.
+    fn count_down(&mut self, elapsed: Duration) -> bool {
+        self.state_timer = self.state_timer.checked_sub(elapsed).unwrap_or_default();
+        self.state_timer == Duration::from_millis(0)
+    }
+    
+    fn advance_cursor(&mut self, elapsed: Duration) -> bool {
+        if self.cursor.is_none() { return false; }
+        
+        self.gravity_timer += elapsed;
+        let mut changed = false;
+        
+        let interval = self.gravity_interval();
+        if interval == Duration::from_millis(0) {
+            self.gravity_timer = interval;
+            while self.move_cursor(MoveDown).is_ok() {
+                changed = true;
+            }
+        } else {
+            while self.gravity_timer >= interval {
+                self.gravity_timer -= interval;
+                changed |= self.move_cursor(MoveDown).is_ok();
+            }
+        }
+        
+        if let Some(lock_timer) = self.lock_timer {
+            if self.is_grounded() {
+                let lock_timer = lock_timer + elapsed;
+                self.lock_timer = Some(lock_timer);
+                if lock_timer >= self.lock_delay {
+                    self.lock_cursor();
+                }
+                changed = true;
+            }
+        }
+        
+        changed
+    }
+    
+    fn is_grounded(&mut self) -> bool {
+        !self.can_move_piece(MoveDown, Selection::Cursor)
+    }
+    
+    /// Updates lock delay after a successful move. Moves and rotations on
+    /// the stack restart the timer up to `LOCK_RESET_LIMIT` times, and
+    /// reaching a new lowest row gives the piece a fresh set of resets.
+    fn cursor_moved(&mut self, movement: Movement) {
+        self.last_movement = Some(movement);
+        let row = self.cursor.as_ref().unwrap().coord.1;
+        
+        if row < self.lowest_row {
+            self.lowest_row = row;
+            self.lock_resets = 0;
+            self.lock_timer = None;
+        } else if movement != MoveDown && movement != SoftDrop &&
+            self.lock_timer.is_some() &&
+            self.lock_resets < LOCK_RESET_LIMIT
+        {
+            self.lock_resets += 1;
+            self.lock_timer = Some(Duration::from_millis(0));
+        }
+        
+        if self.lock_timer.is_none() && self.is_grounded() {
+            self.lock_timer = Some(Duration::from_millis(0));
+        }
+    }
+    
+    fn fill_preview(&mut self) {
+        while self.preview.len() < self.preview_length {
+            self.preview.push_back(self.randomizer.next());
+        }
+    }
+    
+    fn next_kind(&mut self) -> piece::Kind {
+        let kind = match self.preview.pop_front() {
+            Some(kind) => kind,
+            None => self.randomizer.next(),
+        };
+        self.fill_preview();
+        kind
+    }
+    
+    /// The seed this game was started from, if it was started from one.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    fn is_free(&self, location: &coord::Coord) -> bool {
+        location.0 >= 0 &&
+        location.1 >= 0 &&
+        (location.0 as usize) < self.board.width() &&
+        (
+            (location.1 as usize) >= self.board.total_height() ||
+            self.board.get(
+                location.0 as usize,
+                location.1 as usize
+            ).is_none()
+        )
+    }
+
+    fn fits(&self, locations: &[coord::Coord;4]) -> bool {
+        locations.iter().all( |location| self.is_free(location) )
+    }
+    
+    /// Applies the 3-corner rule to the cursor as it locks. A T that got
+    /// there by rotating, with three of the four cells diagonal to its
+    /// center occupied, is a spin; it is a full spin if both corners beside
+    /// its point are occupied or it took the last kick, and a mini otherwise.
+    fn detect_spin(&self) -> score::Spin {
+        use self::coord::Coord as C;
+        use self::piece::Orientation;
+        
+        // The corners that count are the ones around where the piece locks.
+        let cursor = match self.projection.as_ref() {
+            Some(cursor) if cursor.kind == piece::Kind::T => cursor,
+            _ => return score::Spin::None,
+        };
+        match self.last_movement {
+            Some(RotLeft) | Some(RotRight) => {},
+            _ => return score::Spin::None,
+        }
+        
+        let occupied = |offset: C| !self.is_free(&(cursor.coord + offset));
+        let corners = [C(-1,1), C(1,1), C(1,-1), C(-1,-1)]
+            .iter()
+            .filter( |&&corner| occupied(corner) )
+            .count();
+        if corners < 3 { return score::Spin::None; }
+        
+        let front = match cursor.orientation {
+            Orientation::Spawn => [C(-1, 1), C( 1, 1)],
+            Orientation::Right => [C( 1, 1), C( 1,-1)],
+            Orientation::Flip  => [C( 1,-1), C(-1,-1)],
+            Orientation::Left  => [C(-1,-1), C(-1, 1)],
+        };
+        if front.iter().all( |&corner| occupied(corner) ) || self.last_kick == LAST_KICK {
+            score::Spin::Full
+        } else {
+            score::Spin::Mini
         }
     }
 
@@ -52,36 +487,79 @@ impl Game {
             panic!("Tried to evaluate move for nonexistent {:?}.", selection);
         }
 
-        piece.unwrap()
-            .real_locations_when_moved(movement)
-            .iter()
-            .all( |location|
-                location.0 >= 0 &&
-                location.1 >= 0 &&
-                (location.0 as usize) < board::BOARD_WIDTH &&
-                (
-                    (location.1 as usize) >= board::BOARD_HEIGHT ||
-                    self.board.get(
-                        location.0 as usize,
-                        location.1 as usize
-                    ).is_none()
+        self.fits(&piece.unwrap().real_locations_when_moved(movement))
+    }
+    
+    /// Rotates the cursor, trying each SRS kick in order until one fits.
+    fn try_rotate_cursor(&mut self, movement: Movement) -> Result<(),()> {
+        let kick = {
+            let cursor = self.cursor.as_ref().expect("Tried to rotate nonexistent cursor.");
+            let rotated = cursor.real_locations_when_moved(movement);
+            
+            cursor.kicks(movement)
+                .iter()
+                .cloned()
+                .enumerate()
+                .find( |&(_, kick)|
+                    self.fits(
+                        &rotated
+                            .iter()
+                            .map( |location| location + kick )
+                            .collect::<[coord::Coord;4]>()
+                    )
                 )
-            )
+        };
+        
+        let (kick_index, kick) = kick.ok_or(())?;
+        {
+            let cursor = self.cursor.as_mut().unwrap();
+            cursor.do_move(movement);
+            cursor.coord += kick;
+        }
+        self.last_kick = kick_index;
+        self.project_cursor();
+        self.cursor_moved(movement);
+        
+        Ok(())
     }
     
+    /// Moves the cursor on behalf of the player. Soft and hard drops score
+    /// as they move the piece; a hard drop also locks it, so check
+    /// `get_state` and `get_score_event` afterwards.
     pub fn try_move_cursor(&mut self, movement: Movement) -> Result<(),()> {
+        if self.state != GameState::Playing { return Err(()); }
+        self.move_cursor(movement)?;
+        if movement == SoftDrop { self.scoring.soft_drop(1); }
+        Ok(())
+    }
+    
+    fn move_cursor(&mut self, movement: Movement) -> Result<(),()> {
+        if self.cursor.is_none() { return Err(()); }
+        
+        let result = match movement {
+            RotLeft | RotRight => self.try_rotate_cursor(movement),
+            HardDrop => {
+                self.hard_drop();
+                return Ok(());
+            },
+            _ => self.shift_cursor(movement),
+        };
+        
+        if result.is_ok() {
+            let piece = self.cursor.clone().unwrap();
+            self.emit(Event::PieceMoved { movement, piece });
+        }
+        result
+    }
+    
+    fn shift_cursor(&mut self, movement: Movement) -> Result<(),()> {
         if !self.can_move_piece(movement, Selection::Cursor) {
-            return match movement {
-                MoveDown => {
-                    self.place_cursor();
-                    Ok(())
-                },
-                _ => Err(())
-            };
+            return Err(());
         }
 
         self.cursor.as_mut().unwrap().do_move(movement);
-        if movement != MoveDown { self.project_cursor(); }
+        if movement != MoveDown && movement != SoftDrop { self.project_cursor(); }
+        self.cursor_moved(movement);
 
         Ok(())
     }
@@ -93,36 +571,180 @@ impl Game {
         }
     }
 
-    pub fn place_cursor(&mut self) {
-        self.board.place(self.projection.take().unwrap());
-        self.prev_cursor_x = self.cursor.take().unwrap().coord.0 as usize;
+    /// Drops the cursor onto its projection, scoring two points per row,
+    /// and locks it. A drop that moves the piece cancels any spin; one that
+    /// leaves it in place, because it is already resting on the stack, keeps
+    /// the spin.
+    fn hard_drop(&mut self) -> GameState {
+        if let (Some(cursor), Some(projection)) = (self.cursor.as_ref(), self.projection.as_ref()) {
+            let rows = (cursor.coord.1 - projection.coord.1) as u32;
+            self.scoring.hard_drop(rows);
+            if rows > 0 {
+                self.last_movement = Some(HardDrop);
+            }
+        }
+        if let Some(piece) = self.projection.clone() {
+            self.emit(Event::PieceMoved { movement: HardDrop, piece });
+        }
+        self.lock_cursor()
+    }
+    
+    /// Locks the projected cursor into the board and scores it. Locking a
+    /// piece entirely inside the vanish zone, or partly above it, is a lock
+    /// out and ends the game. Clearing lines starts the line clear delay;
+    /// otherwise the next piece spawns straight away.
+    fn lock_cursor(&mut self) -> GameState {
+        if self.cursor.is_none() { return self.state; }
+        
+        self.spin = self.detect_spin();
+        let piece = self.projection.take().unwrap();
+        self.cursor = None;
+        self.lock_timer = None;
+        self.hold_available = true;
+        
+        let locations = piece.real_locations();
+        let locked_out =
+            locations.iter().all( |location| location.1 as usize >= self.board.height() ) ||
+            locations.iter().any( |location| location.1 as usize >= self.board.total_height() );
+        if locked_out {
+            self.end_game();
+            return self.state;
+        }
+        
+        let level = self.scoring.level();
+        self.board.place(piece.clone());
+        let event = self.evaluate_score();
+        let lines = event.lines;
+        self.last_event = Some(event.clone());
+        
+        self.emit(Event::PieceLocked { piece, score: event });
+        if lines > 0 {
+            let clear = self.last_clear.clone().unwrap();
+            self.emit(Event::LinesCleared(clear));
+        }
+        if self.scoring.level() > level {
+            let level = self.scoring.level();
+            self.emit(Event::LevelUp(level));
+        }
+        
+        if lines > 0 && self.line_clear_delay > Duration::from_millis(0) {
+            self.state = GameState::LineClearAnimation;
+            self.state_timer = self.line_clear_delay;
+            self.state
+        } else {
+            self.refill_cursor()
+        }
+    }
+    
+    /// Pushes `rows` rows of garbage up from the bottom, each with a hole in
+    /// column `hole`, which must be on the board. The stack may rise into
+    /// the vanish zone; pushing blocks out of the top of it ends the game.
+    /// The cursor is lifted out of the way if the garbage would overlap it.
+    pub fn add_garbage(&mut self, rows: usize, hole: usize) -> GameState {
+        let mut overflowed = false;
+        for _ in 0..rows {
+            overflowed |= !self.board.push_garbage(hole, named::GRAY);
+        }
+        if overflowed && self.state != GameState::Over {
+            self.end_game();
+        }
+        
+        if self.state == GameState::Over {
+            self.cursor = None;
+            self.projection = None;
+        } else if self.cursor.is_some() {
+            while !self.fits(&self.cursor.as_ref().unwrap().real_locations()) {
+                self.cursor.as_mut().unwrap().coord.1 += 1;
+            }
+            self.project_cursor();
+        }
+        
+        self.state
     }
     
-    pub fn refill_cursor(&mut self) {
+    /// Spawns the next piece and resumes play. If it overlaps the stack,
+    /// that is a block out and the game is over.
+    fn refill_cursor(&mut self) -> GameState {
         if self.cursor.is_some() {
             panic!("Tried to refill cursor when it already has a piece.");
         }
         
-        self.cursor = Some(
-            piece::template::random_at(
-                coord::Coord(self.prev_cursor_x as i8, board::INSERTION_POINT)
-            )
-        );
-
+        let kind = self.next_kind();
+        let piece = self.spawn(kind);
+        
+        if self.fits(&piece.real_locations()) {
+            self.set_cursor(piece.clone());
+            self.state = GameState::Playing;
+            self.emit(Event::PieceSpawned(piece));
+        } else {
+            self.end_game();
+        }
+        
+        self.state
+    }
+    
+    fn set_cursor(&mut self, piece: piece::Piece) {
+        self.lowest_row = piece.coord.1;
+        self.last_movement = None;
+        self.last_kick = 0;
+        self.cursor = Some(piece);
+        self.gravity_timer = Duration::from_millis(0);
+        self.lock_timer = None;
+        self.lock_resets = 0;
+        
         self.project_cursor();
+        if self.is_grounded() {
+            self.lock_timer = Some(Duration::from_millis(0));
+        }
     }
     
-    pub fn evaluate_score(&mut self) -> bool {
-        let add = self.board.clear_lines();
-        if add > 0 {
-            self.score += add as u32;
-            true
-        } else {
-            false
+    /// A new piece at the insertion point, dropped one row straight away if
+    /// there is room, so that it shows even when spawned in the vanish zone.
+    fn spawn(&self, kind: piece::Kind) -> piece::Piece {
+        let mut piece = piece::template::at(kind, self.board.insertion_point());
+        if self.fits(&piece.real_locations_when_moved(MoveDown)) {
+            piece.do_move(MoveDown);
+        }
+        piece
+    }
+    
+    /// Swaps the cursor with the held piece, or with the next piece if
+    /// nothing is held. Allowed once per locked piece. If the piece swapped
+    /// in overlaps the stack, that is a block out and the game is over.
+    pub fn hold_cursor(&mut self) -> Result<(),()> {
+        if self.state != GameState::Playing || !self.hold_available || self.cursor.is_none() {
+            return Err(());
+        }
+        
+        let held = self.cursor.take().expect("Tried to hold nonexistent cursor.").kind;
+        let kind = match self.hold.replace(held) {
+            Some(kind) => kind,
+            None => self.next_kind(),
+        };
+        
+        let cursor = self.spawn(kind);
+        self.hold_available = false;
+        if !self.fits(&cursor.real_locations()) {
+            self.end_game();
+            return Ok(());
         }
+        
+        self.set_cursor(cursor.clone());
+        self.emit(Event::Hold { held, cursor });
+        
+        Ok(())
     }
     
-    pub fn board_iter_with_index(&self) -> board::IterWithIndex {
+    /// Clears completed lines and scores the piece that just locked.
+    fn evaluate_score(&mut self) -> score::ScoreEvent {
+        let clear = self.board.clear_lines();
+        let spin = mem::replace(&mut self.spin, score::Spin::None);
+        let event = self.scoring.lock(clear.lines(), spin, clear.is_perfect_clear());
+        self.last_clear = Some(clear);
+        event
+    }
+    
+    pub fn board_iter_with_index(&self) -> board::IterWithIndex<'_> {
         self.board.iter_with_index()
     }
     
@@ -133,4 +755,753 @@ impl Game {
     pub fn get_projection(&self) -> Option<&piece::Piece> {
         self.projection.as_ref()
     }
+    
+    pub fn get_state(&self) -> GameState {
+        self.state
+    }
+    
+    /// Whole seconds left in the countdown, rounded up.
+    pub fn get_countdown(&self) -> u64 {
+        if self.state != GameState::Countdown &&
+            !(self.state == GameState::Paused && self.paused_state == GameState::Countdown) {
+            return 0;
+        }
+        (self.state_timer.as_millis() as u64).div_ceil(1000)
+    }
+    
+    /// The score for the most recently locked piece.
+    pub fn get_score_event(&self) -> Option<&score::ScoreEvent> {
+        self.last_event.as_ref()
+    }
+    
+    /// How far through the line clear delay the game is, from 0 to 1, or
+    /// `None` outside it.
+    pub fn get_line_clear_progress(&self) -> Option<f32> {
+        let in_line_clear = self.state == GameState::LineClearAnimation ||
+            (self.state == GameState::Paused && self.paused_state == GameState::LineClearAnimation);
+        if !in_line_clear { return None; }
+        
+        let delay = self.line_clear_delay.as_secs_f32();
+        Some(1.0 - self.state_timer.as_secs_f32() / delay)
+    }
+    
+    /// The lines removed when the most recent piece locked.
+    pub fn get_line_clear(&self) -> Option<&board::ClearResult> {
+        self.last_clear.as_ref()
+    }
+    
+    /// How long the cursor has been resting on the stack, if it is.
+    pub fn get_lock_timer(&self) -> Option<Duration> {
+        self.lock_timer
+    }
+    
+    pub fn get_lock_delay(&self) -> Duration {
+        self.lock_delay
+    }
+    
+    pub fn get_score(&self) -> u32 {
+        self.scoring.score()
+    }
+    
+    pub fn get_level(&self) -> u32 {
+        self.scoring.level()
+    }
+    
+    pub fn get_lines(&self) -> u32 {
+        self.scoring.lines()
+    }
+    
+    pub fn get_hold(&self) -> Option<&'static piece::Piece> {
+        self.hold.map(piece::template::of)
+    }
+    
+    pub fn can_hold(&self) -> bool {
+        self.hold_available
+    }
+    
+    /// Templates of the upcoming pieces, next piece first.
+    pub fn get_preview(&self) -> impl Iterator<Item=&'static piece::Piece> + '_ {
+        self.preview
+            .iter()
+            .take(self.preview_length)
+            .map( |&kind| piece::template::of(kind) )
+    }
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::std::time::Duration;
+    use super::{Game, GameState};
+    use super::event::Event;
+    use super::randomizer::Sequence;
+    use super::score::Spin;
+    use super::Movement::*;
+    use super::piece::{template, Kind, Orientation, Piece};
+    use super::coord::Coord as C;
+    
+    fn game_with_cursor(piece: Piece) -> Game {
+        let mut game = Game::new();
+        game.set_cursor(piece);
+        game.state = GameState::Playing;
+        game
+    }
+    
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+    
+    fn first_kinds(game: &mut Game, count: usize) -> Vec<Kind> {
+        (0..count)
+            .map( |_| {
+                game.cursor = None;
+                game.refill_cursor();
+                game.get_cursor().unwrap().kind
+            })
+            .collect()
+    }
+    
+    #[test]
+    fn seeded_games_match() {
+        let mut first = Game::with_seed(0xDEAD_BEEF);
+        let mut second = Game::with_seed(0xDEAD_BEEF);
+        assert_eq!(Some(0xDEAD_BEEF), first.seed());
+        assert_eq!(first_kinds(&mut first, 30), first_kinds(&mut second, 30));
+    }
+    
+    #[test]
+    fn seed_sequence_is_stable() {
+        // Seeds are shared in bug reports, so the pieces they give must not
+        // change from one build to the next.
+        use self::Kind::*;
+        let mut game = Game::with_seed(0xDEAD_BEEF);
+        assert_eq!(
+            vec![I, Z, S, L, T, J, O, O, I, Z, S, J, T, L],
+            first_kinds(&mut game, 14),
+        );
+    }
+    
+    #[test]
+    fn seeds_differ() {
+        let mut first = Game::with_seed(1);
+        let mut second = Game::with_seed(2);
+        assert!(first_kinds(&mut first, 30) != first_kinds(&mut second, 30));
+    }
+    
+    #[test]
+    fn preview_predicts_cursor() {
+        let mut game = Game::with_seed(7);
+        let previewed = game.get_preview().map( |piece| piece.kind ).collect::<Vec<Kind>>();
+        assert_eq!(super::DEFAULT_PREVIEW_LENGTH, previewed.len());
+        assert_eq!(previewed, first_kinds(&mut game, 5));
+    }
+    
+    #[test]
+    fn preview_length_keeps_sequence() {
+        let mut short = Game::with_seed(7);
+        short.set_preview_length(1);
+        assert_eq!(1, short.get_preview().count());
+        
+        let mut long = Game::with_seed(7);
+        long.set_preview_length(9);
+        assert_eq!(first_kinds(&mut short, 20), first_kinds(&mut long, 20));
+    }
+    
+    #[test]
+    fn hold_swaps_once_per_drop() {
+        let mut game = Game::with_seed(3);
+        game.refill_cursor();
+        let first = game.get_cursor().unwrap().kind;
+        let second = game.get_preview().next().unwrap().kind;
+        
+        game.try_move_cursor(MoveLeft).unwrap();
+        game.try_move_cursor(RotRight).unwrap();
+        assert_eq!(Ok(()), game.hold_cursor());
+        assert_eq!(first, game.get_hold().unwrap().kind);
+        assert_eq!(second, game.get_cursor().unwrap().kind);
+        assert_eq!(Err(()), game.hold_cursor());
+        
+        game.try_move_cursor(HardDrop).unwrap();
+        let third = game.get_cursor().unwrap().kind;
+        assert_eq!(Ok(()), game.hold_cursor());
+        assert_eq!(third, game.get_hold().unwrap().kind);
+        
+        let cursor = game.get_cursor().unwrap();
+        assert_eq!(first, cursor.kind);
+        assert_eq!(Orientation::Spawn, cursor.orientation);
+        assert_eq!(game.spawn(first).coord, cursor.coord);
+        assert!(game.get_projection().unwrap().coord.1 < cursor.coord.1);
+    }
+    
+    #[test]
+    fn gravity_follows_interval() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,10)));
+        game.set_speed_curve(vec![Duration::from_millis(100)]);
+        
+        assert!(!game.advance(Duration::from_millis(99)));
+        assert_eq!(C(4,10), game.get_cursor().unwrap().coord);
+        
+        assert!(game.advance(Duration::from_millis(201)));
+        assert_eq!(C(4,7), game.get_cursor().unwrap().coord);
+    }
+    
+    #[test]
+    fn speed_curve_by_level() {
+        let mut game = Game::new();
+        game.set_speed_curve(vec![Duration::from_millis(50), Duration::from_millis(20)]);
+        assert_eq!(Duration::from_millis(50), game.gravity_interval());
+        
+        for _ in 0..3 { game.scoring.lock(4, Spin::None, false); }
+        assert_eq!(2, game.get_level());
+        assert_eq!(Duration::from_millis(20), game.gravity_interval());
+        
+        for _ in 0..17 { game.scoring.lock(4, Spin::None, false); }
+        assert_eq!(9, game.get_level());
+        assert_eq!(Duration::from_millis(20), game.gravity_interval());
+    }
+    
+    #[test]
+    fn lock_after_delay() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,1)));
+        game.set_speed_curve(vec![ms(1000)]);
+        
+        assert_eq!(Ok(()), game.try_move_cursor(MoveDown));
+        assert_eq!(Err(()), game.try_move_cursor(MoveDown));
+        assert_eq!(Some(ms(0)), game.get_lock_timer());
+        
+        game.advance(ms(499));
+        assert_eq!(Some(ms(499)), game.get_lock_timer());
+        
+        game.advance(ms(1));
+        assert!(game.board.get(4, 0).is_some());
+        assert!(game.get_lock_timer().is_none());
+    }
+    
+    #[test]
+    fn moves_reset_lock_delay() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,0)));
+        game.set_speed_curve(vec![ms(1000)]);
+        
+        game.advance(ms(400));
+        game.try_move_cursor(MoveLeft).unwrap();
+        assert_eq!(Some(ms(0)), game.get_lock_timer());
+        
+        game.advance(ms(400));
+        game.try_move_cursor(RotRight).unwrap();
+        game.advance(ms(400));
+        assert!(game.board_iter_with_index().all( |(_, _, square)| square.is_none() ));
+    }
+    
+    #[test]
+    fn lock_reset_limit() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,0)));
+        game.set_speed_curve(vec![ms(10000)]);
+        
+        for index in 0..super::LOCK_RESET_LIMIT {
+            game.advance(ms(100));
+            let movement = if index % 2 == 0 { MoveLeft } else { MoveRight };
+            game.try_move_cursor(movement).unwrap();
+        }
+        assert_eq!(Some(ms(0)), game.get_lock_timer());
+        
+        game.advance(ms(300));
+        game.try_move_cursor(MoveRight).unwrap();
+        assert_eq!(Some(ms(300)), game.get_lock_timer());
+        
+        game.advance(ms(200));
+        assert!(game.board.get(4, 0).is_some());
+    }
+    
+    fn fill(game: &mut Game, cells: &[(i8, i8)]) {
+        for &(x, y) in cells {
+            game.board.place(Piece {
+                offsets: [C(0,0);4],
+                coord: C(x, y),
+                ..template::O
+            });
+        }
+    }
+    
+    /// Row `y` filled but for the four middle columns, which a flat I fills.
+    fn gap_row(y: i8) -> Vec<(i8, i8)> {
+        (0..10).filter( |&x| !(3..7).contains(&x) ).map( |x| (x, y) ).collect()
+    }
+    
+    /// A game dealing `kinds`, over a bottom row that the first I dropped
+    /// straight down clears.
+    fn over_gap(kinds: Vec<Kind>) -> Game {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(kinds)));
+        fill(&mut game, &gap_row(0));
+        game
+    }
+    
+    fn i_over_gap() -> Game {
+        over_gap(vec![Kind::I])
+    }
+    
+    #[test]
+    fn block_out() {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::T])));
+        let column = (0..21).map( |y| (4, y) ).collect::<Vec<_>>();
+        fill(&mut game, &column);
+        
+        assert_eq!(GameState::Over, game.refill_cursor());
+        assert!(game.get_cursor().is_none());
+        assert_eq!(Err(()), game.try_move_cursor(MoveLeft));
+        assert_eq!(Err(()), game.hold_cursor());
+        
+        game.restart();
+        assert_eq!(GameState::Countdown, game.get_state());
+        assert!(game.get_cursor().is_none());
+        game.advance(ms(super::DEFAULT_COUNTDOWN_MS));
+        assert_eq!(GameState::Playing, game.get_state());
+        assert!(game.get_cursor().is_some());
+    }
+    
+    #[test]
+    fn hold_into_block_out() {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::T, Kind::I])));
+        // Blocks the I where it spawns, but not the T.
+        fill(&mut game, &[(6, 19), (6, 20)]);
+        game.refill_cursor();
+        assert_eq!(Kind::T, game.get_cursor().unwrap().kind);
+        
+        assert_eq!(Ok(()), game.hold_cursor());
+        assert_eq!(GameState::Over, game.get_state());
+        assert!(game.get_cursor().is_none());
+        assert_eq!(Err(()), game.try_move_cursor(HardDrop));
+        assert!(game.board.get(3, 20).is_none());
+    }
+    
+    #[test]
+    fn lock_out() {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::T])));
+        let stack = (3..6)
+            .flat_map( |x| (0..20).map(move |y| (x, y)) )
+            .collect::<Vec<_>>();
+        fill(&mut game, &stack);
+        
+        assert_eq!(GameState::Playing, game.refill_cursor());
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(GameState::Over, game.get_state());
+        assert!(game.board.get(4, 20).is_none());
+        assert!(game.board.get(4, 21).is_none());
+    }
+    
+    #[test]
+    fn zero_gravity_interval_drops_to_stack() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,10)));
+        game.set_speed_curve(vec![ms(0)]);
+        
+        assert!(game.advance(ms(16)));
+        assert_eq!(C(4,0), game.get_cursor().unwrap().coord);
+        assert_eq!(0, game.get_score());
+        
+        game.advance(game.get_lock_delay());
+        assert!(game.board.get(4, 0).is_some());
+    }
+    
+    #[test]
+    fn drop_scoring() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,10)));
+        game.try_move_cursor(SoftDrop).unwrap();
+        game.try_move_cursor(SoftDrop).unwrap();
+        game.try_move_cursor(MoveDown).unwrap();
+        game.advance(game.gravity_interval());
+        assert_eq!(2, game.get_score());
+        
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(GameState::Playing, game.get_state());
+        assert_eq!(2 + 2*6, game.get_score());
+        assert_eq!(2 + 2*6, game.get_score_event().unwrap().drop_points);
+        assert!(game.board.get(4, 0).is_some());
+    }
+    
+    #[test]
+    fn perfect_clear() {
+        let mut game = i_over_gap();
+        game.refill_cursor();
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(GameState::LineClearAnimation, game.get_state());
+        
+        let event = game.get_score_event().unwrap();
+        assert_eq!(1, event.lines);
+        assert!(event.perfect_clear);
+        assert_eq!(100 + 800, event.points);
+        assert!(game.get_line_clear().unwrap().is_perfect_clear());
+    }
+    
+    #[test]
+    fn clear_result_lists_rows() {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::I])));
+        let mut stack = (0..10).map( |x| (x, 0) ).collect::<Vec<_>>();
+        stack.extend(gap_row(1));
+        stack.push((0, 2));
+        fill(&mut game, &stack);
+        game.refill_cursor();
+        game.try_move_cursor(HardDrop).unwrap();
+        
+        let clear = game.get_line_clear().unwrap();
+        assert_eq!(vec![0, 1], clear.rows);
+        assert_eq!(2, clear.lines());
+        assert_eq!(20, clear.cells.len());
+        assert!(clear.cells.iter().all( |&(_, y, _)| y < 2 ));
+        assert!(clear.cells.iter().any( |&(x, y, square)| (x, y) == (4, 1) && square.0 == template::I.color ));
+        assert!(!clear.empty);
+        assert!(!clear.is_perfect_clear());
+        assert!(game.board.get(0, 0).is_some());
+    }
+    
+    #[test]
+    fn events_follow_play() {
+        let mut game = over_gap(vec![Kind::I, Kind::O]);
+        let events = game.subscribe();
+        game.set_line_clear_delay(ms(0));
+        
+        game.refill_cursor();
+        game.try_move_cursor(MoveLeft).unwrap();
+        game.hold_cursor().unwrap();
+        game.try_move_cursor(HardDrop).unwrap();
+        
+        let events = events.try_iter().collect::<Vec<Event>>();
+        match events.as_slice() {
+            [
+                Event::PieceSpawned(spawned),
+                Event::PieceMoved { movement: MoveLeft, piece: moved },
+                Event::Hold { held: Kind::I, cursor },
+                Event::PieceMoved { movement: HardDrop, piece: dropped },
+                Event::PieceLocked { piece: locked, score },
+                Event::PieceSpawned(next),
+            ] => {
+                assert_eq!(Kind::I, spawned.kind);
+                assert_eq!(C(3,19), moved.coord);
+                assert_eq!(Kind::O, cursor.kind);
+                assert_eq!(C(4,0), dropped.coord);
+                assert_eq!(dropped.coord, locked.coord);
+                assert_eq!(0, score.lines);
+                assert_eq!(Kind::I, next.kind);
+            },
+            events => panic!("Unexpected events: {:?}", events),
+        }
+    }
+    
+    #[test]
+    fn events_report_clears_and_game_over() {
+        let mut game = i_over_gap();
+        let events = game.subscribe();
+        let dropped = game.subscribe();
+        drop(dropped);
+        for &lines in &[4, 4, 1] { game.scoring.lock(lines, Spin::None, false); }
+        
+        game.refill_cursor();
+        game.try_move_cursor(HardDrop).unwrap();
+        game.add_garbage(41, 0);
+        assert_eq!(1, game.subscribers.len());
+        
+        let events = events.try_iter().collect::<Vec<Event>>();
+        match &events[2..] {
+            [
+                Event::PieceLocked { .. },
+                Event::LinesCleared(clear),
+                Event::LevelUp(2),
+                Event::GameOver { score, lines: 10, seed: None },
+            ] => {
+                assert_eq!(vec![0], clear.rows);
+                assert_eq!(game.get_score(), *score);
+            },
+            events => panic!("Unexpected events: {:?}", events),
+        }
+    }
+    
+    #[test]
+    fn game_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        assert_send(&Game::new());
+    }
+    
+    /// A T pointing down into a slot, with the given cells filled around it.
+    fn t_slot(filled: &[(i8, i8)], last_kick: usize) -> Game {
+        let mut game = Game::new();
+        fill(&mut game, filled);
+        
+        let mut piece = Piece::from_preset(&template::T, C(4,1));
+        piece.do_move(RotRight);
+        piece.do_move(RotRight);
+        game.set_cursor(piece);
+        game.last_movement = Some(RotRight);
+        game.last_kick = last_kick;
+        game
+    }
+    
+    #[test]
+    fn hard_drop_keeps_spin() {
+        let mut game = t_slot(&[(3, 0), (5, 0), (3, 2)], 1);
+        game.state = GameState::Playing;
+        
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(Spin::Full, game.get_score_event().unwrap().spin);
+    }
+    
+    #[test]
+    fn hard_drop_from_air_is_not_spin() {
+        let mut game = t_slot(&[(3, 11), (5, 11), (3, 9)], 1);
+        let mut piece = Piece::from_preset(&template::T, C(4,10));
+        piece.do_move(RotRight);
+        game.set_cursor(piece);
+        game.state = GameState::Playing;
+        
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(Spin::None, game.get_score_event().unwrap().spin);
+        assert_eq!(2*9, game.get_score());
+        
+        // Nor is dropping into a slot that would have counted.
+        let mut game = t_slot(&[(3, 0), (5, 0), (3, 2)], 1);
+        game.cursor.as_mut().unwrap().coord = C(4,10);
+        game.state = GameState::Playing;
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(Spin::None, game.get_score_event().unwrap().spin);
+    }
+    
+    #[test]
+    fn t_spin_double() {
+        let mut filled = (0..10).filter( |&x| x != 4 ).map( |x| (x, 0) ).collect::<Vec<_>>();
+        filled.extend((0..10).filter( |&x| !(3..=5).contains(&x) ).map( |x| (x, 1) ));
+        filled.push((3, 2));
+        let mut game = t_slot(&filled, 0);
+        
+        game.lock_cursor();
+        let event = game.get_score_event().unwrap();
+        assert_eq!(Spin::Full, event.spin);
+        assert_eq!(2, event.lines);
+        assert_eq!("T-SPIN DOUBLE", event.to_string());
+    }
+    
+    #[test]
+    fn mini_t_spin() {
+        let filled = [(3, 0), (3, 2), (5, 2)];
+        
+        let mut game = t_slot(&filled, 1);
+        game.lock_cursor();
+        assert_eq!(Spin::Mini, game.get_score_event().unwrap().spin);
+        
+        let mut game = t_slot(&filled, 4);
+        game.lock_cursor();
+        assert_eq!(Spin::Full, game.get_score_event().unwrap().spin);
+    }
+    
+    #[test]
+    fn spin_needs_rotation() {
+        let mut game = t_slot(&[(3, 0), (5, 0), (3, 2)], 0);
+        game.last_movement = Some(MoveLeft);
+        game.lock_cursor();
+        assert_eq!(Spin::None, game.get_score_event().unwrap().spin);
+    }
+    
+    #[test]
+    fn narrow_board() {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::I])));
+        game.set_board_size(4, 8);
+        
+        assert_eq!(GameState::Playing, game.refill_cursor());
+        assert_eq!(Err(()), game.try_move_cursor(MoveLeft));
+        assert_eq!(Err(()), game.try_move_cursor(MoveRight));
+        
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(1, game.get_score_event().unwrap().lines);
+        assert!(game.board_iter_with_index().all( |(_, _, square)| square.is_none() ));
+        assert_eq!(4*(8 + 20), game.board_iter_with_index().count());
+    }
+    
+    #[test]
+    fn spawn_drops_into_view() {
+        for &kind in [Kind::I, Kind::O, Kind::T, Kind::S, Kind::Z, Kind::J, Kind::L].iter() {
+            let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![kind])));
+            game.refill_cursor();
+            let cursor = game.get_cursor().unwrap();
+            assert!(cursor.real_locations().iter().any( |location| location.1 < 20 ), "{:?}", kind);
+        }
+        
+        // A piece that can't drop spawns where it is.
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::T])));
+        fill(&mut game, &[(4, 19)]);
+        game.refill_cursor();
+        assert_eq!(C(4,20), game.get_cursor().unwrap().coord);
+    }
+    
+    #[test]
+    fn tall_board() {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::O])));
+        game.set_board_size(10, 40);
+        game.refill_cursor();
+        
+        assert_eq!(C(4,39), game.get_cursor().unwrap().coord);
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(2*39, game.get_score());
+    }
+    
+    #[test]
+    fn lock_into_vanish_zone() {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::T])));
+        let stack = (0..3)
+            .flat_map( |x| (0..19).map(move |y| (x, y)) )
+            .collect::<Vec<_>>();
+        fill(&mut game, &stack);
+        
+        game.refill_cursor();
+        assert_eq!(C(4,19), game.get_cursor().unwrap().coord);
+        for _ in 0..3 { game.try_move_cursor(MoveLeft).unwrap(); }
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(GameState::Playing, game.get_state());
+        assert!(game.board.get(1, 20).is_some());
+    }
+    
+    #[test]
+    #[should_panic(expected = "Garbage hole must be in a column below 10, not 10.")]
+    fn garbage_hole_off_board() {
+        Game::new().add_garbage(1, 10);
+    }
+    
+    #[test]
+    fn garbage_lifts_stack() {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::O])));
+        game.set_buffer_height(2);
+        game.refill_cursor();
+        game.try_move_cursor(HardDrop).unwrap();
+        
+        assert_eq!(GameState::Playing, game.add_garbage(19, 0));
+        assert!(game.board.get(4, 20).is_some());
+        assert!(game.board.get(0, 18).is_none());
+        assert!(game.board.get(1, 18).is_some());
+        assert_eq!(C(4,21), game.get_cursor().unwrap().coord);
+        
+        assert_eq!(GameState::Playing, game.add_garbage(1, 0));
+        assert_eq!(GameState::Over, game.add_garbage(1, 0));
+    }
+    
+    #[test]
+    fn countdown_spawns_first_piece() {
+        let mut game = Game::new();
+        assert_eq!(GameState::Countdown, game.get_state());
+        assert_eq!(3, game.get_countdown());
+        assert_eq!(Err(()), game.try_move_cursor(MoveLeft));
+        
+        assert!(!game.advance(ms(500)));
+        assert!(game.advance(ms(500)));
+        assert_eq!(2, game.get_countdown());
+        
+        game.advance(ms(2000));
+        assert_eq!(GameState::Playing, game.get_state());
+        assert_eq!(0, game.get_countdown());
+        assert!(game.get_cursor().is_some());
+    }
+    
+    #[test]
+    fn pause_and_resume() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,10)));
+        game.set_speed_curve(vec![ms(100)]);
+        
+        assert_eq!(Err(()), game.resume());
+        assert_eq!(Ok(()), game.pause());
+        assert_eq!(Err(()), game.pause());
+        assert_eq!(Err(()), game.try_move_cursor(MoveLeft));
+        assert_eq!(Err(()), game.hold_cursor());
+        assert_eq!(Err(()), game.try_move_cursor(HardDrop));
+        assert!(!game.advance(ms(1000)));
+        assert_eq!(C(4,10), game.get_cursor().unwrap().coord);
+        
+        assert_eq!(Ok(()), game.resume());
+        assert_eq!(GameState::Playing, game.get_state());
+        assert_eq!(Ok(()), game.try_move_cursor(MoveLeft));
+        
+        let mut game = Game::new();
+        game.advance(ms(1500));
+        game.pause().unwrap();
+        assert_eq!(2, game.get_countdown());
+        game.resume().unwrap();
+        assert_eq!(GameState::Countdown, game.get_state());
+    }
+    
+    #[test]
+    fn line_clear_redraws_every_tick() {
+        let mut game = i_over_gap();
+        game.set_line_clear_delay(ms(300));
+        game.refill_cursor();
+        game.try_move_cursor(HardDrop).unwrap();
+        
+        let mut ticks = 0;
+        while game.get_state() == GameState::LineClearAnimation {
+            assert!(game.advance(ms(16)), "tick {} wasn't redrawn", ticks);
+            ticks += 1;
+        }
+        assert_eq!(19, ticks);
+        assert_eq!(GameState::Playing, game.get_state());
+    }
+    
+    #[test]
+    fn line_clear_delays_spawn() {
+        let mut game = i_over_gap();
+        game.set_line_clear_delay(ms(300));
+        game.refill_cursor();
+        
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(GameState::LineClearAnimation, game.get_state());
+        assert!(game.get_cursor().is_none());
+        assert_eq!(Err(()), game.try_move_cursor(MoveLeft));
+        
+        assert_eq!(Some(0.0), game.get_line_clear_progress());
+        game.advance(ms(150));
+        assert_eq!(Some(0.5), game.get_line_clear_progress());
+        game.advance(ms(149));
+        assert_eq!(GameState::LineClearAnimation, game.get_state());
+        game.advance(ms(1));
+        assert_eq!(None, game.get_line_clear_progress());
+        assert_eq!(GameState::Playing, game.get_state());
+        assert!(game.get_cursor().is_some());
+        
+        fill(&mut game, &gap_row(0));
+        game.set_line_clear_delay(ms(0));
+        game.try_move_cursor(HardDrop).unwrap();
+        assert_eq!(GameState::Playing, game.get_state());
+        assert!(game.get_cursor().is_some());
+    }
+    
+    #[test]
+    fn rotate_in_open_space() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,5)));
+        assert_eq!(Ok(()), game.try_move_cursor(RotRight));
+        
+        let cursor = game.get_cursor().unwrap();
+        assert_eq!(C(4,5), cursor.coord);
+        assert_eq!(Orientation::Right, cursor.orientation);
+    }
+    
+    #[test]
+    fn kick_off_left_wall() {
+        let mut piece = Piece::from_preset(&template::I, C(0,5));
+        piece.do_move(RotLeft);
+        let mut game = game_with_cursor(piece);
+        
+        assert_eq!(Ok(()), game.try_move_cursor(RotRight));
+        
+        let cursor = game.get_cursor().unwrap();
+        assert_eq!(C(1,5), cursor.coord);
+        assert_eq!(Orientation::Spawn, cursor.orientation);
+        assert_eq!(
+            [C(0,5), C(1,5), C(2,5), C(3,5)],
+            cursor.real_locations()
+        );
+    }
+    
+    #[test]
+    fn kick_off_floor() {
+        let mut game = game_with_cursor(Piece::from_preset(&template::T, C(4,0)));
+        
+        assert_eq!(Ok(()), game.try_move_cursor(RotLeft));
+        assert_eq!(C(5,1), game.get_cursor().unwrap().coord);
+    }
 }
@@ -8,11 +8,118 @@ use self::Movement::*;
 use self::coord::Coord;
 use self::color::Color;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Kind { O, T, L, J, S, Z, I }
+
+impl Kind {
+    pub const ALL: [Kind;7] = [Kind::O, Kind::T, Kind::L, Kind::J, Kind::S, Kind::Z, Kind::I];
+
+    /// Rotation center in doubled coordinates, so that the I and O pieces
+    /// can turn around the corner between four cells.
+    fn rotation_center(&self) -> Coord {
+        match *self {
+            Kind::I => Coord(1, -1),
+            Kind::O => Coord(1,  1),
+            _       => Coord(0,  0),
+        }
+    }
+
+    fn kick_table(&self) -> &'static KickTable {
+        match *self {
+            Kind::I => &kicks::I,
+            Kind::O => &kicks::O,
+            _       => &kicks::JLSTZ,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Orientation { Spawn, Right, Flip, Left }
+
+impl Orientation {
+    pub fn turned(&self, movement: Movement) -> Orientation {
+        use self::Orientation::*;
+        match (movement, *self) {
+            (RotRight, Spawn) => Right,
+            (RotRight, Right) => Flip,
+            (RotRight, Flip)  => Left,
+            (RotRight, Left)  => Spawn,
+            (RotLeft,  Spawn) => Left,
+            (RotLeft,  Left)  => Flip,
+            (RotLeft,  Flip)  => Right,
+            (RotLeft,  Right) => Spawn,
+            (_, orientation)  => orientation,
+        }
+    }
+
+    fn index(&self) -> usize {
+        use self::Orientation::*;
+        match *self {
+            Spawn => 0,
+            Right => 1,
+            Flip  => 2,
+            Left  => 3,
+        }
+    }
+}
+
+/// Kick offsets indexed by starting orientation, then by direction
+/// (clockwise first). Tests are tried in order; the first is always `(0,0)`.
+type KickTable = [[&'static [Coord]; 2]; 4];
+
+mod kicks {
+    use super::KickTable;
+    use super::Coord as C;
+
+    pub const NONE: &[C] = &[C(0,0)];
+
+    pub const O: KickTable = [[NONE, NONE]; 4];
+
+    pub const JLSTZ: KickTable = [
+        [ // 0 -> R, 0 -> L
+            &[C(0,0), C(-1,0), C(-1, 1), C(0,-2), C(-1,-2)],
+            &[C(0,0), C( 1,0), C( 1, 1), C(0,-2), C( 1,-2)],
+        ],
+        [ // R -> 2, R -> 0
+            &[C(0,0), C( 1,0), C( 1,-1), C(0, 2), C( 1, 2)],
+            &[C(0,0), C( 1,0), C( 1,-1), C(0, 2), C( 1, 2)],
+        ],
+        [ // 2 -> L, 2 -> R
+            &[C(0,0), C( 1,0), C( 1, 1), C(0,-2), C( 1,-2)],
+            &[C(0,0), C(-1,0), C(-1, 1), C(0,-2), C(-1,-2)],
+        ],
+        [ // L -> 0, L -> 2
+            &[C(0,0), C(-1,0), C(-1,-1), C(0, 2), C(-1, 2)],
+            &[C(0,0), C(-1,0), C(-1,-1), C(0, 2), C(-1, 2)],
+        ],
+    ];
+
+    pub const I: KickTable = [
+        [ // 0 -> R, 0 -> L
+            &[C(0,0), C(-2,0), C( 1,0), C(-2,-1), C( 1, 2)],
+            &[C(0,0), C(-1,0), C( 2,0), C(-1, 2), C( 2,-1)],
+        ],
+        [ // R -> 2, R -> 0
+            &[C(0,0), C(-1,0), C( 2,0), C(-1, 2), C( 2,-1)],
+            &[C(0,0), C( 2,0), C(-1,0), C( 2, 1), C(-1,-2)],
+        ],
+        [ // 2 -> L, 2 -> R
+            &[C(0,0), C( 2,0), C(-1,0), C( 2, 1), C(-1,-2)],
+            &[C(0,0), C( 1,0), C(-2,0), C( 1,-2), C(-2, 1)],
+        ],
+        [ // L -> 0, L -> 2
+            &[C(0,0), C( 1,0), C(-2,0), C( 1,-2), C(-2, 1)],
+            &[C(0,0), C(-2,0), C( 1,0), C(-2,-1), C( 1, 2)],
+        ],
+    ];
+}
+
 #[derive(Clone, Debug)]
 pub struct Piece {
     pub offsets: [Coord;4],
     pub color: Color,
-    rotation: bool,
+    pub kind: Kind,
+    pub orientation: Orientation,
     pub coord: Coord,
 }
 
@@ -21,8 +128,9 @@ impl Piece {
         Piece {
             offsets: preset.offsets,
             color: preset.color,
-            rotation: preset.rotation,
-            coord: coord,
+            kind: preset.kind,
+            orientation: preset.orientation,
+            coord,
         }
     }
 
@@ -33,123 +141,135 @@ impl Piece {
             .collect::<[Coord;4]>()
     }
 
+    fn rotated_offsets(&self, movement: Movement) -> [Coord;4] {
+        let rotation_function = match movement {
+            RotRight => Coord::turn_right,
+            RotLeft  => Coord::turn_left,
+            _ => unreachable!(),
+        };
+        let center = self.kind.rotation_center();
+
+        self.offsets
+            .iter()
+            .map( |offset| Coord(2*offset.0 - center.0, 2*offset.1 - center.1) )
+            .map( |doubled| rotation_function(&doubled) )
+            .map( |turned| Coord((turned.0 + center.0)/2, (turned.1 + center.1)/2) )
+            .collect::<[Coord;4]>()
+    }
+
+    /// Locations the piece would occupy after `movement`, before any kick.
     pub fn real_locations_when_moved(&self, movement: Movement) -> [Coord;4] {
+        let offsets = match movement {
+            RotRight | RotLeft => self.rotated_offsets(movement),
+            _ => self.offsets,
+        };
+        let coord = self.coord + Piece::displacement(movement);
+        offsets
+            .iter()
+            .map( |offset| offset + coord )
+            .collect::<[Coord;4]>()
+    }
+
+    /// How far `movement` shifts the piece. Every drop is one row down: a
+    /// piece knows nothing of the board, so `Game` repeats a hard drop's
+    /// step until the piece lands. Rotations turn the piece in place.
+    pub fn displacement(movement: Movement) -> Coord {
         match movement {
-            MoveLeft | MoveRight | MoveDown => {
-                let displacement = match movement {
-                    MoveLeft  => Coord(-1,  0),
-                    MoveRight => Coord( 1,  0),
-                    MoveDown  => Coord( 0, -1),
-                    _ => unreachable!(),
-                };
-                self.offsets
-                    .iter()
-                    .map( |offset| offset + self.coord + displacement )
-                    .collect::<[Coord;4]>()
-            },
-            RotRight | RotLeft => {
-                if self.rotation == false { return self.offsets.clone(); }
-                
-                let rotation_function = match movement {
-                    RotRight => Coord::turn_right,
-                    RotLeft  => Coord::turn_left,
-                    _ => unreachable!(),
-                };
-                self.offsets
-                    .iter()
-                    .map(rotation_function)
-                    .map( |offset| offset + self.coord )
-                    .collect::<[Coord;4]>()
-            },
+            MoveLeft  => Coord(-1,  0),
+            MoveRight => Coord( 1,  0),
+            MoveDown | SoftDrop | HardDrop => Coord( 0, -1),
+            RotRight | RotLeft => Coord( 0,  0),
         }
     }
-    
+
+    /// SRS kick offsets to try, in order, when performing `movement`.
+    pub fn kicks(&self, movement: Movement) -> &'static [Coord] {
+        let direction = match movement {
+            RotRight => 0,
+            RotLeft  => 1,
+            _ => return kicks::NONE,
+        };
+        self.kind.kick_table()[self.orientation.index()][direction]
+    }
 
     pub fn do_move(&mut self, movement: Movement) {
-        match movement {
-            MoveLeft | MoveRight | MoveDown => {
-                let displacement = match movement {
-                    MoveLeft  => Coord(-1,  0),
-                    MoveRight => Coord( 1,  0),
-                    MoveDown  => Coord( 0, -1),
-                    _ => unreachable!(),
-                };
-                self.coord += displacement;
-            },
-            RotRight | RotLeft => {
-                if self.rotation == false { return; }
-                
-                let rotation_function = match movement {
-                    RotRight => Coord::turn_right,
-                    RotLeft  => Coord::turn_left,
-                    _ => unreachable!(),
-                };
-                self.offsets = self.offsets
-                    .iter()
-                    .map(rotation_function)
-                    .collect::<[Coord;4]>();
-            },
+        if let RotRight | RotLeft = movement {
+            self.offsets = self.rotated_offsets(movement);
+            self.orientation = self.orientation.turned(movement);
         }
+        self.coord += Piece::displacement(movement);
     }
 }
 
 pub mod template {
-    use ::rand;
-    use self::rand::Rng;
-    use super::Piece;
+    use super::{Piece, Kind, Orientation};
     use super::Coord as C;
     use super::color::named::*;
 
     pub const O: Piece = Piece {
         offsets: [C(0,0), C(0,1), C(1,0), C(1, 1)],
         color: YELLOW,
+        kind: Kind::O,
+        orientation: Orientation::Spawn,
         coord: C(0,0),
-        rotation: false,
     };
     pub const T: Piece = Piece {
         offsets: [C(-1,0), C(0,0), C(1,0), C(0,1)],
         color: PURPLE,
+        kind: Kind::T,
+        orientation: Orientation::Spawn,
         coord: C(0,0),
-        rotation: true,
     };
     pub const L: Piece = Piece {
-        offsets: [C(0,2), C(0,1), C(0,0), C(1,0)],
+        offsets: [C(-1,0), C(0,0), C(1,0), C(1,1)],
         color: ORANGE,
+        kind: Kind::L,
+        orientation: Orientation::Spawn,
         coord: C(0,0),
-        rotation: true,
     };
     pub const J: Piece = Piece {
-        offsets: [C(0,2), C(0,1), C(0,0), C(-1,0)],
+        offsets: [C(-1,1), C(-1,0), C(0,0), C(1,0)],
         color: BLUE,
+        kind: Kind::J,
+        orientation: Orientation::Spawn,
         coord: C(0,0),
-        rotation: true,
     };
     pub const S: Piece = Piece {
         offsets: [C(-1,0), C(0,0), C(0,1), C(1,1)],
         color: GREEN,
+        kind: Kind::S,
+        orientation: Orientation::Spawn,
         coord: C(0,0),
-        rotation: true,
     };
     pub const Z: Piece = Piece {
         offsets: [C(-1,1), C(0,1), C(0,0), C(1,0)],
         color: RED,
+        kind: Kind::Z,
+        orientation: Orientation::Spawn,
         coord: C(0,0),
-        rotation: true,
     };
     pub const I: Piece = Piece {
         offsets: [C(-1,0), C(0,0), C(1,0), C(2,0)],
         color: CYAN,
+        kind: Kind::I,
+        orientation: Orientation::Spawn,
         coord: C(0,0),
-        rotation: true,
     };
     
-    const PIECES: [&'static Piece;7] = [&O, &T, &L, &J, &S, &Z, &I];
+    pub fn of(kind: Kind) -> &'static Piece {
+        match kind {
+            Kind::O => &O,
+            Kind::T => &T,
+            Kind::L => &L,
+            Kind::J => &J,
+            Kind::S => &S,
+            Kind::Z => &Z,
+            Kind::I => &I,
+        }
+    }
     
-    pub fn random_at(coord: C) -> Piece {
-        Piece::from_preset(
-            rand::thread_rng().choose(&PIECES).unwrap(),
-            coord
-        )
+    pub fn at(kind: Kind, coord: C) -> Piece {
+        Piece::from_preset(of(kind), coord)
     }
 }
 
@@ -158,6 +278,7 @@ mod tests {
     use super::Piece;
     use super::template;
     use super::Movement::*;
+    use super::Orientation::*;
     use super::Coord as C;
     
     const ORIGIN: C = C(4,5);
@@ -181,6 +302,14 @@ mod tests {
         );
     }
     
+    #[test]
+    fn hard_drop_steps_down() {
+        let mut piece = Piece::from_preset(&template::O, ORIGIN);
+        assert_eq!(piece.real_locations_when_moved(MoveDown), piece.real_locations_when_moved(HardDrop));
+        piece.do_move(HardDrop);
+        assert_eq!(C(4,4), piece.coord);
+    }
+    
     #[test]
     fn when_rotated() {
         let piece = Piece::from_preset(&template::T, ORIGIN);
@@ -223,4 +352,48 @@ mod tests {
             piece_right.offsets
         );
     }
+    
+    #[test]
+    fn rotate_i_about_true_center() {
+        let mut piece = Piece::from_preset(&template::I, ORIGIN);
+        piece.do_move(RotRight);
+        assert_eq!(
+            [C(5,6), C(5,5), C(5,4), C(5,3)],
+            piece.real_locations()
+        );
+        piece.do_move(RotRight);
+        assert_eq!(
+            [C(6,4), C(5,4), C(4,4), C(3,4)],
+            piece.real_locations()
+        );
+    }
+    
+    #[test]
+    fn rotate_o_in_place() {
+        let mut piece = Piece::from_preset(&template::O, ORIGIN);
+        piece.do_move(RotLeft);
+        
+        let mut locations = piece.real_locations();
+        locations.sort_by_key( |coord| (coord.0, coord.1) );
+        assert_eq!([C(4,5), C(4,6), C(5,5), C(5,6)], locations);
+    }
+    
+    #[test]
+    fn orientation_cycle() {
+        let mut piece = Piece::from_preset(&template::J, ORIGIN);
+        for &expected in [Right, Flip, Left, Spawn].iter() {
+            piece.do_move(RotRight);
+            assert_eq!(expected, piece.orientation);
+        }
+        assert_eq!(template::J.offsets, piece.offsets);
+    }
+    
+    #[test]
+    fn kick_order() {
+        let piece = Piece::from_preset(&template::T, ORIGIN);
+        assert_eq!(C(0,0), piece.kicks(RotRight)[0]);
+        assert_eq!(5, piece.kicks(RotRight).len());
+        assert_eq!(1, Piece::from_preset(&template::O, ORIGIN).kicks(RotLeft).len());
+        assert_eq!(1, piece.kicks(MoveLeft).len());
+    }
 }
@@ -0,0 +1,161 @@
+use ::rand::Rng;
+use super::piece::Kind;
+
+/// Source of the piece sequence fed to `Game::refill_cursor`. Randomizers
+/// are `Send` so that a `Game` can be handed to another thread.
+pub trait Randomizer: Send {
+    fn next(&mut self) -> Kind;
+}
+
+/// Deals all seven pieces in a shuffled bag before refilling it.
+pub struct Bag<R: Rng> {
+    rng: R,
+    bag: Vec<Kind>,
+}
+
+impl<R: Rng> Bag<R> {
+    pub fn new(rng: R) -> Bag<R> {
+        Bag {
+            rng,
+            bag: Vec::with_capacity(Kind::ALL.len()),
+        }
+    }
+}
+
+impl<R: Rng + Send> Randomizer for Bag<R> {
+    fn next(&mut self) -> Kind {
+        if self.bag.is_empty() {
+            self.bag.extend_from_slice(&Kind::ALL);
+            self.rng.shuffle(&mut self.bag);
+        }
+        self.bag.pop().unwrap()
+    }
+}
+
+/// Picks each piece independently and uniformly.
+pub struct Uniform<R: Rng> {
+    rng: R,
+}
+
+impl<R: Rng> Uniform<R> {
+    pub fn new(rng: R) -> Uniform<R> {
+        Uniform { rng }
+    }
+}
+
+impl<R: Rng + Send> Randomizer for Uniform<R> {
+    fn next(&mut self) -> Kind {
+        *self.rng.choose(&Kind::ALL).unwrap()
+    }
+}
+
+const HISTORY_ROLLS: usize = 4;
+
+/// The TGM randomizer: remembers the last four pieces and rerolls up to
+/// four times to avoid repeating one of them. The history starts as all
+/// Z's, and the first piece is never an S, Z or O.
+pub struct History<R: Rng> {
+    rng: R,
+    history: [Kind;4],
+    first: bool,
+}
+
+impl<R: Rng> History<R> {
+    pub fn new(rng: R) -> History<R> {
+        History {
+            rng,
+            history: [Kind::Z;4],
+            first: true,
+        }
+    }
+}
+
+impl<R: Rng + Send> Randomizer for History<R> {
+    fn next(&mut self) -> Kind {
+        let kind = if self.first {
+            self.first = false;
+            *self.rng.choose(&[Kind::I, Kind::J, Kind::L, Kind::T]).unwrap()
+        } else {
+            let mut kind = *self.rng.choose(&Kind::ALL).unwrap();
+            for _ in 1..HISTORY_ROLLS {
+                if !self.history.contains(&kind) { break; }
+                kind = *self.rng.choose(&Kind::ALL).unwrap();
+            }
+            kind
+        };
+        
+        self.history.rotate_left(1);
+        self.history[3] = kind;
+        kind
+    }
+}
+
+/// Replays a fixed sequence, starting over once it runs out.
+pub struct Sequence {
+    kinds: Vec<Kind>,
+    index: usize,
+}
+
+impl Sequence {
+    pub fn new(kinds: Vec<Kind>) -> Sequence {
+        if kinds.is_empty() {
+            panic!("Tried to create an empty piece sequence.");
+        }
+        
+        Sequence {
+            kinds,
+            index: 0,
+        }
+    }
+}
+
+impl Randomizer for Sequence {
+    fn next(&mut self) -> Kind {
+        let kind = self.kinds[self.index];
+        self.index = (self.index + 1) % self.kinds.len();
+        kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::rand::{SeedableRng, XorShiftRng};
+    use super::*;
+    use super::Kind::*;
+    
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([1, 2, 3, 4])
+    }
+    
+    #[test]
+    fn bag_deals_every_piece() {
+        let mut bag = Bag::new(rng());
+        for _ in 0..3 {
+            let mut dealt = (0..7).map( |_| bag.next() ).collect::<Vec<Kind>>();
+            dealt.sort_by_key( |&kind| kind as u8 );
+            assert_eq!(Kind::ALL.to_vec(), dealt);
+        }
+    }
+    
+    #[test]
+    fn uniform_deals_every_piece() {
+        let mut uniform = Uniform::new(rng());
+        let dealt = (0..200).map( |_| uniform.next() ).collect::<Vec<Kind>>();
+        assert!(Kind::ALL.iter().all( |kind| dealt.contains(kind) ));
+    }
+    
+    #[test]
+    fn history_first_piece() {
+        for seed in 1..50 {
+            let mut history = History::new(XorShiftRng::from_seed([seed, 2, 3, 4]));
+            assert!(![S, Z, O].contains(&history.next()));
+        }
+    }
+    
+    #[test]
+    fn sequence_repeats() {
+        let mut sequence = Sequence::new(vec![I, T, O]);
+        let dealt = (0..7).map( |_| sequence.next() ).collect::<Vec<Kind>>();
+        assert_eq!(vec![I, T, O, I, T, O, I], dealt);
+    }
+}
@@ -0,0 +1,295 @@
+use ::std::fmt;
+
+pub const LINES_PER_LEVEL: u32 = 10;
+
+const CLEAR_POINTS: [u32;5] = [0, 100, 300, 500, 800];
+const PERFECT_CLEAR_POINTS: [u32;5] = [0, 800, 1200, 1800, 2000];
+const BACK_TO_BACK_PERFECT_TETRIS_POINTS: u32 = 3200;
+const T_SPIN_POINTS: [u32;4] = [400, 800, 1200, 1600];
+const MINI_T_SPIN_POINTS: [u32;3] = [100, 200, 400];
+const COMBO_POINTS: u32 = 50;
+const SOFT_DROP_POINTS: u32 = 1;
+const HARD_DROP_POINTS: u32 = 2;
+
+/// Whether a T piece locked as a spin, judged by the 3-corner rule.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+/// What a single lock was worth and why.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreEvent {
+    /// Lines cleared by the lock.
+    pub lines: u8,
+    pub spin: Spin,
+    /// Level the clear was scored at.
+    pub level: u32,
+    /// Points for the clear, including combo, back-to-back and
+    /// perfect-clear bonuses.
+    pub points: u32,
+    /// Soft and hard drop points earned by the piece before it locked.
+    pub drop_points: u32,
+    /// Number of consecutive clears before this one, if it continues a chain.
+    pub combo: Option<u32>,
+    pub back_to_back: bool,
+    pub perfect_clear: bool,
+}
+
+impl ScoreEvent {
+    pub fn total(&self) -> u32 {
+        self.points + self.drop_points
+    }
+}
+
+/// Names the clear the way the HUD announces it, e.g. `B2B T-SPIN DOUBLE`.
+/// Plain locks with nothing to announce display as an empty string.
+impl fmt::Display for ScoreEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut words = Vec::new();
+        
+        if self.back_to_back { words.push("B2B".to_string()); }
+        match self.spin {
+            Spin::Full => words.push("T-SPIN".to_string()),
+            Spin::Mini => words.push("MINI T-SPIN".to_string()),
+            Spin::None => {},
+        }
+        match self.lines {
+            1 => words.push("SINGLE".to_string()),
+            2 => words.push("DOUBLE".to_string()),
+            3 => words.push("TRIPLE".to_string()),
+            4 => words.push("TETRIS".to_string()),
+            _ => {},
+        }
+        if let Some(combo) = self.combo {
+            words.push(format!("COMBO {}", combo));
+        }
+        if self.perfect_clear { words.push("PERFECT CLEAR".to_string()); }
+        
+        write!(f, "{}", words.join(" "))
+    }
+}
+
+/// Guideline scoring: tracks score, level, lines, combo and back-to-back.
+pub struct Scoring {
+    score: u32,
+    lines: u32,
+    level: u32,
+    combo: Option<u32>,
+    back_to_back: bool,
+    drop_points: u32,
+}
+
+impl Scoring {
+    pub fn new() -> Scoring {
+        Scoring {
+            score: 0,
+            lines: 0,
+            level: 1,
+            combo: None,
+            back_to_back: false,
+            drop_points: 0,
+        }
+    }
+    
+    pub fn score(&self) -> u32 { self.score }
+    pub fn lines(&self) -> u32 { self.lines }
+    pub fn level(&self) -> u32 { self.level }
+    
+    pub fn soft_drop(&mut self, cells: u32) {
+        self.add_drop_points(SOFT_DROP_POINTS * cells);
+    }
+    
+    pub fn hard_drop(&mut self, cells: u32) {
+        self.add_drop_points(HARD_DROP_POINTS * cells);
+    }
+    
+    fn add_drop_points(&mut self, points: u32) {
+        self.drop_points += points;
+        self.score += points;
+    }
+    
+    /// Scores a locked piece that cleared `lines` lines.
+    pub fn lock(&mut self, lines: u8, spin: Spin, perfect_clear: bool) -> ScoreEvent {
+        let level = self.level;
+        let drop_points = self.drop_points;
+        self.drop_points = 0;
+        
+        if lines == 0 {
+            let points = match spin {
+                Spin::Full => T_SPIN_POINTS[0],
+                Spin::Mini => MINI_T_SPIN_POINTS[0],
+                Spin::None => 0,
+            } * level;
+            self.score += points;
+            self.combo = None;
+            
+            return ScoreEvent {
+                lines,
+                spin,
+                level,
+                points,
+                drop_points,
+                combo: None,
+                back_to_back: false,
+                perfect_clear: false,
+            };
+        }
+        
+        let difficult = lines >= 4 || spin != Spin::None;
+        let back_to_back = difficult && self.back_to_back;
+        self.back_to_back = difficult;
+        
+        let mut points = match spin {
+            Spin::Full => T_SPIN_POINTS[lines.min(3) as usize],
+            Spin::Mini => MINI_T_SPIN_POINTS[lines.min(2) as usize],
+            Spin::None => CLEAR_POINTS[lines as usize],
+        };
+        if back_to_back { points = points * 3 / 2; }
+        
+        if perfect_clear {
+            points += if back_to_back && lines == 4 {
+                BACK_TO_BACK_PERFECT_TETRIS_POINTS
+            } else {
+                PERFECT_CLEAR_POINTS[lines as usize]
+            };
+        }
+        
+        let combo = self.combo.map( |combo| combo + 1 );
+        self.combo = Some(combo.unwrap_or(0));
+        if let Some(combo) = combo {
+            points += COMBO_POINTS * combo;
+        }
+        
+        points *= level;
+        self.score += points;
+        self.lines += lines as u32;
+        self.level = 1 + self.lines / LINES_PER_LEVEL;
+        
+        ScoreEvent {
+            lines,
+            spin,
+            level,
+            points,
+            drop_points,
+            combo,
+            back_to_back,
+            perfect_clear,
+        }
+    }
+}
+
+impl Default for Scoring {
+    fn default() -> Scoring {
+        Scoring::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Scoring, Spin};
+    
+    #[test]
+    fn clear_table() {
+        let points = (1..5)
+            .map( |lines| {
+                let mut scoring = Scoring::new();
+                scoring.lock(lines, Spin::None, false).points
+            })
+            .collect::<Vec<u32>>();
+        assert_eq!(vec![100, 300, 500, 800], points);
+    }
+    
+    #[test]
+    fn level_multiplier() {
+        let mut scoring = Scoring::new();
+        for _ in 0..3 {
+            scoring.lock(4, Spin::None, false);
+            scoring.lock(0, Spin::None, false);
+        }
+        assert_eq!(2, scoring.level());
+        assert_eq!(12, scoring.lines());
+        
+        let event = scoring.lock(1, Spin::None, false);
+        assert_eq!(2, event.level);
+        assert_eq!(200, event.points);
+    }
+    
+    #[test]
+    fn combo_chain() {
+        let mut scoring = Scoring::new();
+        assert_eq!(None, scoring.lock(1, Spin::None, false).combo);
+        
+        let event = scoring.lock(1, Spin::None, false);
+        assert_eq!(Some(1), event.combo);
+        assert_eq!(150, event.points);
+        
+        assert_eq!(Some(2), scoring.lock(2, Spin::None, false).combo);
+        scoring.lock(0, Spin::None, false);
+        assert_eq!(None, scoring.lock(1, Spin::None, false).combo);
+    }
+    
+    #[test]
+    fn back_to_back() {
+        let mut scoring = Scoring::new();
+        assert!(!scoring.lock(4, Spin::None, false).back_to_back);
+        scoring.lock(0, Spin::None, false);
+        
+        let event = scoring.lock(4, Spin::None, false);
+        assert!(event.back_to_back);
+        assert_eq!(1200, event.points);
+        
+        scoring.lock(1, Spin::None, false);
+        assert!(!scoring.lock(4, Spin::None, false).back_to_back);
+    }
+    
+    #[test]
+    fn perfect_clear() {
+        let mut scoring = Scoring::new();
+        let event = scoring.lock(2, Spin::None, true);
+        assert!(event.perfect_clear);
+        assert_eq!(300 + 1200, event.points);
+    }
+    
+    #[test]
+    fn drop_points() {
+        let mut scoring = Scoring::new();
+        scoring.soft_drop(3);
+        scoring.hard_drop(5);
+        assert_eq!(13, scoring.score());
+        
+        let event = scoring.lock(0, Spin::None, false);
+        assert_eq!(13, event.drop_points);
+        assert_eq!(13, event.total());
+        assert_eq!(0, scoring.lock(0, Spin::None, false).drop_points);
+    }
+    
+    #[test]
+    fn t_spins() {
+        let mut scoring = Scoring::new();
+        assert_eq!(400, scoring.lock(0, Spin::Full, false).points);
+        assert_eq!(100, scoring.lock(0, Spin::Mini, false).points);
+        assert_eq!(1200, scoring.lock(2, Spin::Full, false).points);
+        
+        let event = scoring.lock(1, Spin::Mini, false);
+        assert!(event.back_to_back);
+        assert_eq!(300 + 50, event.points);
+        
+        assert!(scoring.lock(4, Spin::None, false).back_to_back);
+    }
+    
+    #[test]
+    fn display() {
+        let mut scoring = Scoring::new();
+        assert_eq!("", scoring.lock(0, Spin::None, false).to_string());
+        assert_eq!("T-SPIN DOUBLE", scoring.lock(2, Spin::Full, false).to_string());
+        assert_eq!(
+            "B2B TETRIS COMBO 1 PERFECT CLEAR",
+            scoring.lock(4, Spin::None, true).to_string()
+        );
+        scoring.lock(0, Spin::None, false);
+        assert_eq!("MINI T-SPIN", scoring.lock(0, Spin::Mini, false).to_string());
+    }
+}
@@ -0,0 +1,197 @@
+use ::std::path::{Path, PathBuf};
+
+use super::config;
+
+/// Something a key can be bound to.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateLeft,
+    RotateRight,
+    Hold,
+    Pause,
+    Restart,
+    Quit,
+}
+
+use self::Action::*;
+
+impl Action {
+    pub const ALL: [Action;10] = [
+        MoveLeft, MoveRight, SoftDrop, HardDrop, RotateLeft, RotateRight,
+        Hold, Pause, Restart, Quit,
+    ];
+    
+    /// The action's name in a bindings file.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MoveLeft    => "move_left",
+            MoveRight   => "move_right",
+            SoftDrop    => "soft_drop",
+            HardDrop    => "hard_drop",
+            RotateLeft  => "rotate_left",
+            RotateRight => "rotate_right",
+            Hold        => "hold",
+            Pause       => "pause",
+            Restart     => "restart",
+            Quit        => "quit",
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.iter().cloned().find( |action| action.name() == name )
+    }
+}
+
+/// Keys bound to each action unless a bindings file says otherwise, by
+/// X keysym name.
+const DEFAULTS: &[(Action, &[&str])] = &[
+    (MoveLeft,    &["Left"]),
+    (MoveRight,   &["Right"]),
+    (SoftDrop,    &["Down"]),
+    (HardDrop,    &["Up", "space"]),
+    (RotateLeft,  &["z", "Control_L"]),
+    (RotateRight, &["x", "KP_Insert"]),
+    (Hold,        &["c", "Shift_L"]),
+    (Pause,       &["p", "Escape"]),
+    (Restart,     &["r"]),
+    (Quit,        &["q"]),
+];
+
+/// Which keys, by X keysym name, trigger which actions. Any number of keys
+/// can be bound to an action, but each key triggers only one. Keys are
+/// named by what they produce without shift or Num Lock, so keypad 0 is
+/// `KP_Insert` rather than `KP_0`.
+///
+/// A bindings file has one line per action it changes, naming the action
+/// and then the keys that replace its defaults:
+///
+/// ```text
+/// # Comments start with a hash.
+/// rotate_right = x Up
+/// hard_drop = space
+/// hold =
+/// ```
+///
+/// Binding a key takes it away from whichever action had it before, and
+/// an action with no keys listed is unbound.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    keys: Vec<(String, Action)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Bindings {
+        Bindings {
+            keys: DEFAULTS
+                .iter()
+                .flat_map( |&(action, keys)| keys.iter().map(move |&key| (key.to_string(), action)) )
+                .collect(),
+        }
+    }
+}
+
+impl Bindings {
+    /// The default bindings, changed by each line of `text`.
+    pub fn parse(text: &str) -> Result<Bindings, String> {
+        let mut bindings = Bindings::default();
+        
+        for (line, name, keys) in config::entries(text)? {
+            let action = match Action::from_name(name) {
+                Some(action) => action,
+                None => return Err(format!("line {}: unknown action `{}`", line, name)),
+            };
+            
+            bindings.bind(action, keys.split_whitespace().map(Bindings::normalize).collect());
+        }
+        
+        Ok(bindings)
+    }
+    
+    pub fn load(path: &Path) -> Result<Bindings, String> {
+        Bindings::parse(&config::read(path)?)
+    }
+    
+    /// Where the bindings file lives: `tetroids/bindings.conf` under
+    /// `$XDG_CONFIG_HOME`, or under `~/.config` if that isn't set.
+    pub fn config_path() -> Option<PathBuf> {
+        config::path("bindings.conf")
+    }
+    
+    /// Replaces the keys bound to `action`.
+    pub fn bind(&mut self, action: Action, keys: Vec<String>) {
+        self.keys.retain( |&(ref key, bound)| bound != action && !keys.contains(key) );
+        self.keys.extend(keys.into_iter().map( |key| (key, action) ));
+    }
+    
+    /// Every binding, as a keysym name and the action it triggers.
+    pub fn iter(&self) -> impl Iterator<Item=(&str, Action)> {
+        self.keys.iter().map( |&(ref key, action)| (key.as_str(), action) )
+    }
+    
+    pub fn keys(&self, action: Action) -> Vec<&str> {
+        self.iter()
+            .filter( |&(_, bound)| bound == action )
+            .map( |(key, _)| key )
+            .collect()
+    }
+    
+    /// Keys are looked up without shift, so single letters are bound by
+    /// their lowercase keysym whichever case the file uses.
+    fn normalize(key: &str) -> String {
+        if key.len() == 1 { key.to_ascii_lowercase() } else { key.to_string() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Bindings};
+    use super::Action::*;
+    
+    #[test]
+    fn defaults_cover_every_action() {
+        let bindings = Bindings::default();
+        for &action in Action::ALL.iter() {
+            assert!(!bindings.keys(action).is_empty(), "{:?} is unbound", action);
+            assert_eq!(Some(action), Action::from_name(action.name()));
+        }
+        assert_eq!(vec!["Up", "space"], bindings.keys(HardDrop));
+    }
+    
+    #[test]
+    fn file_replaces_listed_actions() {
+        let bindings = Bindings::parse("
+            # Guideline rotation on the arrow keys.
+            rotate_right = Up X   # and x
+            hard_drop = space
+            hold =
+        ").unwrap();
+        
+        assert_eq!(vec!["Up", "x"], bindings.keys(RotateRight));
+        assert_eq!(vec!["space"], bindings.keys(HardDrop));
+        assert!(bindings.keys(Hold).is_empty());
+        assert_eq!(vec!["Left"], bindings.keys(MoveLeft));
+    }
+    
+    #[test]
+    fn rebinding_moves_key() {
+        let bindings = Bindings::parse("quit = Escape").unwrap();
+        assert_eq!(vec!["Escape"], bindings.keys(Quit));
+        assert_eq!(vec!["p"], bindings.keys(Pause));
+    }
+    
+    #[test]
+    fn bad_lines() {
+        assert_eq!(
+            Err("line 2: unknown action `spin`".to_string()),
+            Bindings::parse("pause = p\nspin = s"),
+        );
+        assert_eq!(
+            Err("line 1: expected `name = value`".to_string()),
+            Bindings::parse("hold c"),
+        );
+    }
+}
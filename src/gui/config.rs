@@ -0,0 +1,47 @@
+use ::std::env;
+use ::std::fs;
+use ::std::path::{Path, PathBuf};
+
+/// Where config file `name` lives: under `tetroids` in `$XDG_CONFIG_HOME`,
+/// or in `~/.config` if that isn't set.
+pub fn path(name: &str) -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("tetroids").join(name))
+}
+
+pub fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err( |error| error.to_string() )
+}
+
+/// The `name = value` lines of a config file, with their line numbers.
+/// Everything after a `#` is a comment, and blank lines are skipped.
+pub fn entries(text: &str) -> Result<Vec<(usize, &str, &str)>, String> {
+    let mut entries = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() { continue; }
+        
+        let mut parts = line.splitn(2, '=');
+        let name = parts.next().unwrap().trim();
+        match parts.next() {
+            Some(value) => entries.push((index + 1, name, value.trim())),
+            None => return Err(format!("line {}: expected `name = value`", index + 1)),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::entries;
+    
+    #[test]
+    fn skips_comments_and_blanks() {
+        let text = "# heading\n\n  a = 1 2  # trailing\nb =\n";
+        assert_eq!(Ok(vec![(3, "a", "1 2"), (4, "b", "")]), entries(text));
+        assert_eq!(Err("line 2: expected `name = value`".to_string()), entries("a = 1\nb"));
+    }
+}
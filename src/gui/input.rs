@@ -1,34 +1,47 @@
 use ::x11::{xlib, xinput2};
+use ::std::ffi;
 use ::std::os;
 
-// const WINDOW_EVENTS: &[i32] = &[xinput2::XI_KeyPress];
+use super::bindings::{Action, Bindings};
 
-pub enum Key {
-    ArrowRight = 114,
-    ArrowLeft  = 113,
-    ArrowUp    = 111,
-    ArrowDown  = 116,
-    NumPad0    =  90,
-}
+// const WINDOW_EVENTS: &[i32] = &[xinput2::XI_KeyPress];
 
-impl Key {
-    pub fn from(other: i32) -> Option<Self> {
-        use self::Key::*;
-        match other {
-            114 => Some(ArrowRight),
-            113 => Some(ArrowLeft),
-            111 => Some(ArrowUp),
-            116 => Some(ArrowDown),
-            90  => Some(NumPad0),
-            _ => None,
+/// Looks up the keysym for every bound key name, so key events can be
+/// matched against them. Names X doesn't know are reported and left out.
+pub fn resolve(bindings: &Bindings) -> Vec<(xlib::KeySym, Action)> {
+    let mut keysyms = Vec::new();
+    for (name, action) in bindings.iter() {
+        let keysym = match ffi::CString::new(name) {
+            Ok(name) => unsafe { xlib::XStringToKeysym(name.as_ptr()) },
+            Err(_) => 0,
+        };
+        if keysym == 0 {
+            println!("Ignoring unknown key '{}' bound to {}", name, action.name());
+        } else {
+            keysyms.push((keysym, action));
         }
     }
+    keysyms
+}
+
+/// The unshifted keysym of the key pressed, in whichever keyboard layout
+/// was active at the time, so bindings follow the labels on the keys
+/// rather than their positions.
+pub unsafe fn keysym(display_ptr: *mut xlib::Display, event: &xinput2::XIDeviceEvent) -> xlib::KeySym {
+    xlib::XkbKeycodeToKeysym(
+        display_ptr,
+        event.detail as os::raw::c_uchar,
+        event.group.effective,
+        0,
+    )
 }
 
 pub unsafe fn select_events(display_ptr: *mut xlib::_XDisplay, window: xlib::Window) {
-    let mut mask: [os::raw::c_uchar;1] = [0];
+    let mut mask: [os::raw::c_uchar;2] = [0;2];
     
     xinput2::XISetMask(&mut mask, xinput2::XI_KeyPress);
+    xinput2::XISetMask(&mut mask, xinput2::XI_KeyRelease);
+    xinput2::XISetMask(&mut mask, xinput2::XI_FocusOut);
     
     let mut input_event_mask = xinput2::XIEventMask {
         deviceid: xinput2::XIAllMasterDevices,
@@ -1,6 +1,11 @@
 #![allow(dead_code)]
 
+pub mod bindings;
+mod config;
 mod input;
+mod renderer;
+mod repeat;
+pub mod settings;
 mod xcolor;
 
 use ::x11::{xlib, xinput2};
@@ -13,13 +18,18 @@ use ::std::thread;
 use ::std::sync;
 
 use game;
-use self::input::Key;
+use game::GameState;
+use render;
+use render::KeyHints;
+use render::animation::LineClearStyle;
+use render::layout::Layout;
+use self::bindings::Action;
+use self::renderer::X11Renderer;
+use self::repeat::AutoRepeat;
+use self::settings::Settings;
 
-const WINDOW_PADDING: i32 = 24;
-const WINDOW_WIDTH: os::raw::c_uint = 120 + 2*WINDOW_PADDING as u32;
-const WINDOW_HEIGHT: os::raw::c_uint = 240 + 2*WINDOW_PADDING as u32;
 const WINDOW_TITLE: &str = "Tetroids";
-const INITIAL_TICK_MS: u64 = 1000;
+const TICK_MS: u64 = 16;
 
 #[derive(Copy, Clone)]
 enum DurationOrQuit {
@@ -34,15 +44,28 @@ pub struct GUI {
     
     width: os::raw::c_uint,
     height: os::raw::c_uint,
+    renderer: X11Renderer,
+    peek_rows: usize,
+    line_clear: LineClearStyle,
+    line_clear_ms: u64,
+    keys: Vec<(xlib::KeySym, Action)>,
+    repeat: AutoRepeat,
+    hints: KeyHints,
     
     wm_delete_window: xlib::Atom,
     wm_protocols: xlib::Atom,
+    tick: xlib::Atom,
+    
+    prev_state: GameState,
+    last_clear: String,
 }
 
 impl GUI {
     unsafe fn initialize_window(
         display_ptr: *mut xlib::_XDisplay,
         mut wm_delete_window: xlib::Atom,
+        layout: &Layout,
+        min_layout: &Layout,
     ) -> xlib::Window {
         
         let screen_num = xlib::XDefaultScreen(display_ptr);
@@ -50,16 +73,25 @@ impl GUI {
         
         let mut attributes: xlib::XSetWindowAttributes = mem::zeroed();
         attributes.background_pixel = xlib::XWhitePixel(display_ptr, screen_num);
+        attributes.event_mask = xlib::StructureNotifyMask | xlib::ExposureMask;
         
         let window = xlib::XCreateWindow(
             display_ptr, root, 0, 0,
-            WINDOW_WIDTH, WINDOW_HEIGHT, 0, 0,
+            layout.width as os::raw::c_uint, layout.height as os::raw::c_uint, 0, 0,
             xlib::InputOutput as os::raw::c_uint,
             ptr::null_mut(),
-            xlib::CWBackPixel,
+            xlib::CWBackPixel | xlib::CWEventMask,
             &mut attributes,
         );
         
+        let size_hints = xlib::XAllocSizeHints();
+        if size_hints.is_null() { panic!("Failed to allocate size hints."); }
+        (*size_hints).flags = xlib::PMinSize;
+        (*size_hints).min_width = min_layout.width;
+        (*size_hints).min_height = min_layout.height;
+        xlib::XSetWMNormalHints(display_ptr, window, size_hints);
+        xlib::XFree(size_hints as *mut os::raw::c_void);
+        
         xlib::XStoreName(
             display_ptr,
             window,
@@ -77,7 +109,9 @@ impl GUI {
         window
     }
     
-    pub fn new() -> GUI {
+    pub fn new(game: &game::Game, settings: Settings) -> GUI {
+        let layout = Layout::new(game, settings.peek_rows);
+
         let display_ptr = unsafe { xlib::XOpenDisplay(ptr::null()) };
         if display_ptr.is_null() { panic!("Failed to open XDisplay"); }
         
@@ -95,9 +129,21 @@ impl GUI {
                 xlib::False
             )
         };
-        if wm_delete_window == 0 || wm_protocols == 0 { panic!("Failed to load Xlib Atoms."); }
+        let tick = unsafe {
+            xlib::XInternAtom(
+                display_ptr,
+                ffi::CString::new("TETROIDS_TICK").unwrap().as_ptr(),
+                xlib::False
+            )
+        };
+        if wm_delete_window == 0 || wm_protocols == 0 || tick == 0 {
+            panic!("Failed to load Xlib Atoms.");
+        }
         
-        let window = unsafe { self::GUI::initialize_window(display_ptr, wm_delete_window) };
+        let min_layout = Layout::with_cell(game, settings.peek_rows, settings.min_cell, 0, 0);
+        let window = unsafe {
+            self::GUI::initialize_window(display_ptr, wm_delete_window, &layout, &min_layout)
+        };
         unsafe { self::input::select_events(display_ptr, window); }
 
         let gfx_context = unsafe {
@@ -110,15 +156,29 @@ impl GUI {
         };
         
         GUI {
-            display_ptr: display_ptr,
-            window: window,
-            gfx_context: gfx_context,
+            display_ptr,
+            window,
+            gfx_context,
+            
+            width: layout.width as os::raw::c_uint,
+            height: layout.height as os::raw::c_uint,
+            renderer: X11Renderer::new(display_ptr, window, gfx_context, layout, settings.ghost),
+            peek_rows: settings.peek_rows,
+            line_clear: settings.line_clear,
+            line_clear_ms: settings.line_clear_ms,
+            keys: self::input::resolve(&settings.bindings),
+            repeat: AutoRepeat::new(settings.das_ms, settings.arr_ms, settings.soft_drop_ms),
+            hints: KeyHints {
+                pause: settings.bindings.keys(Action::Pause).join("/"),
+                restart: settings.bindings.keys(Action::Restart).join("/"),
+            },
             
-            width: WINDOW_WIDTH,
-            height: WINDOW_HEIGHT,
+            wm_delete_window,
+            wm_protocols,
+            tick,
             
-            wm_delete_window: wm_delete_window,
-            wm_protocols: wm_protocols,
+            prev_state: GameState::Playing,
+            last_clear: String::new(),
         }
     }
     
@@ -129,32 +189,99 @@ impl GUI {
         }
     }
     
-    fn game_try_move(game: &mut game::Game, key: Key) -> Result<(),()> {
+    fn movement(action: Action) -> Option<game::Movement> {
         use game::Movement::*;
-        let movement = match key {
-            Key::ArrowLeft => MoveLeft,
-            Key::ArrowRight => MoveRight,
-            Key::ArrowDown => MoveDown,
-            Key::NumPad0 => RotRight,
-            Key::ArrowUp => unreachable!(),
-        };
+        match action {
+            Action::MoveLeft    => Some(MoveLeft),
+            Action::MoveRight   => Some(MoveRight),
+            Action::SoftDrop    => Some(SoftDrop),
+            Action::HardDrop    => Some(HardDrop),
+            Action::RotateLeft  => Some(RotLeft),
+            Action::RotateRight => Some(RotRight),
+            _ => None,
+        }
+    }
+    
+    /// Carries out `action`, returning false if it quits the game.
+    fn perform(game: &mut game::Game, action: Action) -> bool {
+        if let Some(movement) = self::GUI::movement(action) {
+            game.try_move_cursor(movement).ok();
+            return true;
+        }
         
-        game.try_move_cursor(movement)
+        match action {
+            Action::Hold => { game.hold_cursor().ok(); },
+            Action::Pause => {
+                if game.get_state() == GameState::Paused {
+                    game.resume().ok();
+                } else {
+                    game.pause().ok();
+                }
+            },
+            Action::Restart => game.restart(),
+            Action::Quit => return false,
+            _ => (),
+        }
+        true
+    }
+    
+    /// Makes the moves held keys have repeated since the last tick, stopping
+    /// each once the piece can go no further. Returns whether anything moved.
+    fn auto_repeat(&mut self, game: &mut game::Game, elapsed: time::Duration) -> bool {
+        let mut moved = false;
+        for (action, times) in self.repeat.advance(elapsed) {
+            let movement = self::GUI::movement(action).unwrap();
+            for _ in 0..times {
+                if game.try_move_cursor(movement).is_err() { break; }
+                moved = true;
+            }
+        }
+        moved
     }
     
-    fn handle_client_message(&self, event: xlib::XEvent) -> bool {
+    fn handle_client_message(
+        &mut self, event: xlib::XEvent,
+        game: &mut game::Game,
+        last_tick: &mut time::Instant,
+    ) -> bool {
         let message: xlib::XClientMessageEvent = From::from(event);
+        if message.message_type == self.tick {
+            let now = time::Instant::now();
+            let moved = self.auto_repeat(game, now - *last_tick);
+            if game.advance(now - *last_tick) || moved {
+                self.update(game);
+            }
+            *last_tick = now;
+            return true;
+        }
+        
         message.message_type != self.wm_protocols ||
             message.format != 32 ||
             message.data.get_long(0) as xlib::Atom != self.wm_delete_window
     }
     
-    fn handle_configure_notify(&mut self, event: xlib::XEvent) -> bool {
+    /// Scales the layout to fit the window whenever it changes size.
+    fn handle_configure_notify(&mut self, event: xlib::XEvent, game: &game::Game) -> bool {
         let configure_event: xlib::XConfigureEvent = From::from(event);
+        let (width, height) = (configure_event.width, configure_event.height);
+        if (width as os::raw::c_uint, height as os::raw::c_uint) == (self.width, self.height) {
+            return true;
+        }
         
-        self.width  = configure_event.width  as os::raw::c_uint;
-        self.height = configure_event.height as os::raw::c_uint;
-        println!("Resizing to {}x{}.", self.width, self.height);
+        self.width  = width  as os::raw::c_uint;
+        self.height = height as os::raw::c_uint;
+        self.renderer.resize(Layout::fit(game, self.peek_rows, width, height), self.width, self.height);
+        self.render(game);
+        true
+    }
+    
+    /// Restores exposed parts of the window from the last frame, once the
+    /// last of a batch of exposures arrives.
+    fn handle_expose(&mut self, event: xlib::XEvent) -> bool {
+        let expose_event: xlib::XExposeEvent = From::from(event);
+        if expose_event.count == 0 {
+            self.renderer.redraw();
+        }
         true
     }
     
@@ -169,163 +296,161 @@ impl GUI {
             panic!("Failed to retrieve xinput event data.");
         }
         
-        if cookie.evtype == xinput2::XI_KeyPress {
-            let event_data: &xinput2::XIDeviceEvent = unsafe { mem::transmute(cookie.data) };
-            if let Some(key) = Key::from(event_data.detail) {
-                let result = match key {
-                    Key::ArrowRight |
-                    Key::ArrowLeft |
-                    Key::ArrowDown |
-                    Key::NumPad0 => self::GUI::game_try_move(game, key),
-                    Key::ArrowUp => {
-                        game.place_cursor();
-                        Ok(())
-                    },
-                };
-                
-                if game.get_cursor().is_none() {
-                    game.refill_cursor();
-                
-                }
-                self.render(game);
-                
-                if game.evaluate_score() {
-                    thread::sleep(time::Duration::from_millis(300));
-                    game.project_cursor();
-                    self.render(game);
-                }
+        // Copy out what is needed so the event data can be freed straight away.
+        let key = match cookie.evtype {
+            xinput2::XI_KeyPress | xinput2::XI_KeyRelease => {
+                let event_data = unsafe { &*(cookie.data as *const xinput2::XIDeviceEvent) };
+                let keysym = unsafe { self::input::keysym(self.display_ptr, event_data) };
+                Some((keysym, event_data.flags & xinput2::XIKeyRepeat != 0))
+            },
+            _ => None,
+        };
+        let evtype = cookie.evtype;
+        unsafe { xlib::XFreeEventData(self.display_ptr, &mut cookie); }
+        
+        if evtype == xinput2::XI_FocusOut {
+            // Releases go elsewhere once focus is lost.
+            self.repeat.release_all();
+        }
+        let (keysym, autorepeat) = match key {
+            Some(key) => key,
+            None => return true,
+        };
+        let action = self.keys.iter().find( |&&(bound, _)| bound == keysym ).map( |&(_, action)| action );
+        let action = match action {
+            Some(action) => action,
+            None => return true,
+        };
+        
+        if evtype == xinput2::XI_KeyRelease {
+            self.repeat.release(action);
+        } else if !autorepeat && self.repeat.press(action) {
+            // X server autorepeats are ignored; held keys repeat on the tick.
+            if !self::GUI::perform(game, action) {
+                return false;
             }
+            self.update(game);
         }
         
         true
     }
     
+    /// Picks up the latest clear label and state changes, then redraws.
+    fn update(&mut self, game: &mut game::Game) {
+        if game.get_state() == GameState::Countdown {
+            self.last_clear.clear();
+        } else if let Some(event) = game.get_score_event() {
+            let label = event.to_string();
+            if !label.is_empty() {
+                self.last_clear = label;
+            }
+        }
+        
+        if game.get_state() == GameState::Over && self.prev_state != GameState::Over {
+            if let Some(seed) = game.seed() {
+                println!("Game over. Seed: {}", seed);
+            }
+        }
+        self.prev_state = game.get_state();
+        self.render(game);
+    }
+    
     fn start_timing_thread(
         &self, tick: sync::Arc<sync::Mutex<DurationOrQuit>>
     ) -> thread::JoinHandle<()> {
-        let thread_display_ptr = self.display_ptr.clone() as usize;
-        let thread_window = self.window.clone();
+        let thread_window = self.window;
+        let tick_atom = self.tick;
         
         thread::spawn(move || {
-            let mut event: xlib::XKeyPressedEvent = unsafe { mem::uninitialized() };
-            // configure event
+            // Xlib connections are not thread-safe, so the timer gets its own.
+            let display_ptr = unsafe { xlib::XOpenDisplay(ptr::null()) };
+            if display_ptr.is_null() { panic!("Failed to open XDisplay for timing thread"); }
             
-            while let DurationOrQuit::Dur(sleep_time) = *tick.lock().unwrap() {
+            let mut message: xlib::XClientMessageEvent = unsafe { mem::zeroed() };
+            message.type_ = xlib::ClientMessage;
+            message.window = thread_window;
+            message.message_type = tick_atom;
+            message.format = 32;
+            let mut event = xlib::XEvent::from(message);
+            
+            loop {
+                let sleep_time = match *tick.lock().unwrap() {
+                    DurationOrQuit::Dur(sleep_time) => sleep_time,
+                    DurationOrQuit::Quit => break,
+                };
                 thread::sleep(sleep_time);
                 unsafe {
                     xlib::XSendEvent(
-                        thread_display_ptr as *mut xlib::_XDisplay,
+                        display_ptr,
                         thread_window,
-                        0,
-                        0,
-                        (&mut event as *mut xlib::XKeyEvent) as *mut xlib::XEvent,
+                        xlib::False,
+                        xlib::NoEventMask,
+                        &mut event,
                     );
+                    xlib::XFlush(display_ptr);
                 }
             }
+            
+            unsafe { xlib::XCloseDisplay(display_ptr); }
         })
     }
     
     pub fn play(&mut self, game: &mut game::Game) {
         unsafe { xlib::XMapWindow(self.display_ptr, self.window); }
-        let mut event: xlib::XEvent = unsafe { mem::uninitialized() };
+        let mut event: xlib::XEvent = unsafe { mem::zeroed() };
         
-        game.refill_cursor();
+        // The animation runs for exactly as long as the game holds back the
+        // next piece.
+        let line_clear_ms = match self.line_clear {
+            LineClearStyle::Off => 0,
+            _ => self.line_clear_ms,
+        };
+        game.set_line_clear_delay(time::Duration::from_millis(line_clear_ms));
         self.render(game);
         
         let tick = sync::Arc::new(
             sync::Mutex::new(
                 DurationOrQuit::Dur(
-                    time::Duration::from_millis(INITIAL_TICK_MS)
+                    time::Duration::from_millis(TICK_MS)
                 )
             )
         );
         
-        // let timing_thread = self.start_timing_thread(tick.clone());
+        let timing_thread = self.start_timing_thread(tick.clone());
+        let mut last_tick = time::Instant::now();
         
         let mut running = true;
         while running {
             unsafe { xlib::XNextEvent(self.display_ptr, &mut event); }
             running = match event.get_type() {
-                xlib::ClientMessage   => self.handle_client_message(event),
-                xlib::ConfigureNotify => self.handle_configure_notify(event),
+                xlib::ClientMessage   => self.handle_client_message(event, game, &mut last_tick),
+                xlib::ConfigureNotify => self.handle_configure_notify(event, game),
+                xlib::Expose          => self.handle_expose(event),
                 xlib::GenericEvent    => self.handle_generic_event(event, game),
+                // The rest of what StructureNotifyMask selects, which only
+                // ConfigureNotify was selected for.
+                xlib::MapNotify | xlib::UnmapNotify | xlib::ReparentNotify |
+                xlib::GravityNotify | xlib::CirculateNotify | xlib::DestroyNotify => true,
                 _ => {
                     println!("Received unhandled event '{}'", event.get_type());
                     true
                 },
             };
-            
-            if let DurationOrQuit::Dur(mut value) = *tick.lock().unwrap() {
-                value -= time::Duration::from_millis(10);
-            }
         }
         
-        //timing_thread.join().unwrap();
-    }
-    
-    pub fn render(&mut self, game: &game::Game) {
-        unsafe {
-            xlib::XClearWindow(self.display_ptr, self.window);
-            // draw border
-            xlib::XDrawRectangle(
-                self.display_ptr,
-                self.window,
-                self.gfx_context,
-                WINDOW_PADDING/2, WINDOW_PADDING/2,
-                (120 + WINDOW_PADDING) as u32,
-                (240 + WINDOW_PADDING) as u32,
-            );
-        }
+        *tick.lock().unwrap() = DurationOrQuit::Quit;
+        timing_thread.join().unwrap();
         
-        // draw board
-        println!("Rendering Board");
-        for (x_index, y_index, square) in game.board_iter_with_index() {
-            if let Some(square) = square {
-                unsafe {
-                    xlib::XFillRectangle(
-                        self.display_ptr,
-                        self.window,
-                        self.gfx_context,
-                             12*x_index as i32  + WINDOW_PADDING + 1,
-                        228-(12*y_index as i32) + WINDOW_PADDING + 1,
-                        10, 10,
-                    );
-                }
-            }
-        }
-        // draw cursor and projection
-        if let Some(ref cursor) = game.get_cursor() {
-            println!("Rendering Piece");
-            for coord in cursor.real_locations().iter() {
-                unsafe {
-                    xlib::XFillRectangle(
-                        self.display_ptr,
-                        self.window,
-                        self.gfx_context,
-                             12*coord.0 as i32  + WINDOW_PADDING + 1,
-                        228-(12*coord.1 as i32) + WINDOW_PADDING + 1,
-                        10, 10,
-                    );
-                }
-            }
-        }
-        // draw projection
-        if let Some(ref projection) = game.get_projection() {
-            println!("Rendering Projection");
-            for coord in projection.real_locations().iter() {
-                unsafe {
-                    xlib::XFillRectangle(
-                        self.display_ptr,
-                        self.window,
-                        self.gfx_context,
-                             12*coord.0 as i32  + WINDOW_PADDING + 1,
-                        228-(12*coord.1 as i32) + WINDOW_PADDING + 1,
-                        10, 10,
-                    );
-                }
+        if game.get_state() != GameState::Over {
+            if let Some(seed) = game.seed() {
+                println!("Quit. Seed: {}", seed);
             }
         }
     }
+    
+    pub fn render(&mut self, game: &game::Game) {
+        render::draw_game(&mut self.renderer, game, self.line_clear, &self.last_clear, &self.hints);
+    }
 }
 
 impl Drop for GUI {
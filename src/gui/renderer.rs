@@ -0,0 +1,346 @@
+use ::x11::xlib;
+use ::std::ffi;
+use ::std::os::raw::c_ulong;
+
+use game::Piece;
+use game::color::Color;
+use render::Renderer;
+use render::layout::*;
+use super::xcolor;
+
+/// How the projection of the falling piece is drawn.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GhostStyle {
+    /// An outline of each cell.
+    Outline,
+    /// Every other pixel of each cell, so the board shows through.
+    Stipple,
+    /// Not drawn at all.
+    Off,
+}
+
+/// Checkerboard bitmap for stippled cells, one byte per row.
+const STIPPLE: [u8;2] = [0b01, 0b10];
+
+/// Draws frames into an off-screen buffer the size of an X11 window, and
+/// copies each finished frame to the window in one go.
+pub struct X11Renderer {
+    display_ptr: *mut xlib::Display,
+    window: xlib::Window,
+    gfx_context: xlib::GC,
+    layout: Layout,
+    buffer: xlib::Pixmap,
+    width: u32,
+    height: u32,
+    ghost: GhostStyle,
+    stipple: xlib::Pixmap,
+    
+    colormap: xlib::Colormap,
+    black: c_ulong,
+    white: c_ulong,
+    /// Pixels allocated so far, by the color they were allocated for.
+    pixels: Vec<(Color, c_ulong)>,
+}
+
+impl X11Renderer {
+    pub fn new(
+        display_ptr: *mut xlib::Display,
+        window: xlib::Window,
+        gfx_context: xlib::GC,
+        layout: Layout,
+        ghost: GhostStyle,
+    ) -> X11Renderer {
+        let (colormap, black, white) = unsafe {
+            let screen_num = xlib::XDefaultScreen(display_ptr);
+            (
+                xlib::XDefaultColormap(display_ptr, screen_num),
+                xlib::XBlackPixel(display_ptr, screen_num),
+                xlib::XWhitePixel(display_ptr, screen_num),
+            )
+        };
+        let (width, height) = (layout.width as u32, layout.height as u32);
+        
+        let stipple = unsafe {
+            xlib::XCreateBitmapFromData(
+                display_ptr,
+                window,
+                STIPPLE.as_ptr() as *const _,
+                2, 2,
+            )
+        };
+        
+        let mut renderer = X11Renderer {
+            display_ptr,
+            window,
+            gfx_context,
+            layout,
+            buffer: X11Renderer::create_buffer(display_ptr, window, width, height),
+            width,
+            height,
+            ghost,
+            stipple,
+            
+            colormap,
+            black,
+            white,
+            pixels: Vec::new(),
+        };
+        for &(color, _) in xcolor::PALETTE.iter() {
+            renderer.pixel(color);
+        }
+        renderer
+    }
+    
+    /// The pixel value for `color`, allocated from the default colormap the
+    /// first time it is asked for. Falls back to black if the colormap is
+    /// full.
+    fn pixel(&mut self, color: Color) -> c_ulong {
+        if let Some(&(_, pixel)) = self.pixels.iter().find( |&&(known, _)| known == color ) {
+            return pixel;
+        }
+        
+        let mut xcolor = xcolor::of(color);
+        let allocated = unsafe { xlib::XAllocColor(self.display_ptr, self.colormap, &mut xcolor) };
+        let pixel = if allocated == 0 { self.black } else { xcolor.pixel };
+        self.pixels.push((color, pixel));
+        pixel
+    }
+    
+    fn create_buffer(
+        display_ptr: *mut xlib::Display,
+        window: xlib::Window,
+        width: u32,
+        height: u32,
+    ) -> xlib::Pixmap {
+        unsafe {
+            let depth = xlib::XDefaultDepth(display_ptr, xlib::XDefaultScreen(display_ptr));
+            xlib::XCreatePixmap(display_ptr, window, width, height, depth as u32)
+        }
+    }
+    
+    /// Lays frames out afresh for a window that is now `width` by `height`.
+    pub fn resize(&mut self, layout: Layout, width: u32, height: u32) {
+        unsafe { xlib::XFreePixmap(self.display_ptr, self.buffer); }
+        self.buffer = X11Renderer::create_buffer(self.display_ptr, self.window, width, height);
+        self.width = width;
+        self.height = height;
+        self.layout = layout;
+    }
+    
+    /// Copies the last finished frame to the window again, for when part
+    /// of it has been exposed.
+    pub fn redraw(&self) {
+        unsafe {
+            xlib::XCopyArea(
+                self.display_ptr,
+                self.buffer,
+                self.window,
+                self.gfx_context,
+                0, 0,
+                self.width, self.height,
+                0, 0,
+            );
+            xlib::XFlush(self.display_ptr);
+        }
+    }
+    
+    fn set_foreground(&self, pixel: c_ulong) {
+        unsafe { xlib::XSetForeground(self.display_ptr, self.gfx_context, pixel); }
+    }
+    
+    fn fill_white(&self, x: i32, y: i32, width: u32, height: u32) {
+        self.set_foreground(self.white);
+        unsafe {
+            xlib::XFillRectangle(
+                self.display_ptr,
+                self.buffer,
+                self.gfx_context,
+                x, y,
+                width, height,
+            );
+        }
+    }
+    
+    fn set_color(&mut self, color: Color) {
+        let pixel = self.pixel(color);
+        self.set_foreground(pixel);
+    }
+    
+    fn draw_text(&self, x: i32, y: i32, text: &str) {
+        let text = ffi::CString::new(text).unwrap();
+        unsafe {
+            xlib::XDrawString(
+                self.display_ptr,
+                self.buffer,
+                self.gfx_context,
+                x, y,
+                text.as_ptr(),
+                text.as_bytes().len() as i32,
+            );
+        }
+    }
+    
+    fn draw_frame(&self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe {
+            xlib::XDrawRectangle(
+                self.display_ptr,
+                self.buffer,
+                self.gfx_context,
+                x - self.layout.padding/2, y - self.layout.padding/2,
+                (width + self.layout.padding) as u32,
+                (height + self.layout.padding) as u32,
+            );
+        }
+    }
+    
+    /// Draws a piece template in the side panel, outlined if `filled` is false.
+    fn draw_panel_piece(&self, piece: &Piece, top: i32, filled: bool) {
+        let draw_function = if filled { xlib::XFillRectangle } else { xlib::XDrawRectangle };
+        let preview_cell = self.layout.preview_cell;
+        
+        for offset in piece.offsets.iter() {
+            unsafe {
+                draw_function(
+                    self.display_ptr,
+                    self.buffer,
+                    self.gfx_context,
+                    self.layout.panel_x + preview_cell*(offset.0 as i32 + 1),
+                    top + preview_cell*(1 - offset.1 as i32),
+                    (preview_cell - 1) as u32, (preview_cell - 1) as u32,
+                );
+            }
+        }
+    }
+    
+    fn fill_board_cell(&self, x: i32, y: i32) {
+        if !self.layout.shows_row(y) { return; }
+        unsafe {
+            xlib::XFillRectangle(
+                self.display_ptr,
+                self.buffer,
+                self.gfx_context,
+                self.layout.cell_x(x),
+                self.layout.cell_y(y),
+                self.layout.cell_fill as u32, self.layout.cell_fill as u32,
+            );
+        }
+    }
+    
+    fn outline_board_cell(&self, x: i32, y: i32) {
+        if !self.layout.shows_row(y) { return; }
+        unsafe {
+            // Rectangle outlines are one pixel larger than their size.
+            xlib::XDrawRectangle(
+                self.display_ptr,
+                self.buffer,
+                self.gfx_context,
+                self.layout.cell_x(x),
+                self.layout.cell_y(y),
+                (self.layout.cell_fill - 1) as u32, (self.layout.cell_fill - 1) as u32,
+            );
+        }
+    }
+    
+    fn fill_board_piece(&self, piece: &Piece) {
+        for coord in piece.real_locations().iter() {
+            self.fill_board_cell(coord.0 as i32, coord.1 as i32);
+        }
+    }
+}
+
+impl Renderer for X11Renderer {
+    fn clear(&mut self) {
+        let layout = &self.layout;
+        self.fill_white(0, 0, self.width, self.height);
+        self.set_foreground(self.black);
+        self.draw_frame(layout.board_x, layout.board_y, layout.board_width, layout.board_height);
+        self.draw_frame(layout.panel_x, layout.hold_y, layout.panel_width, layout.preview_spacing);
+        self.draw_frame(layout.panel_x, layout.preview_y, layout.panel_width, layout.preview_height);
+    }
+    
+    fn draw_cell(&mut self, x: usize, y: usize, color: Color) {
+        self.set_color(color);
+        self.fill_board_cell(x as i32, y as i32);
+    }
+    
+    fn draw_ghost(&mut self, piece: &Piece) {
+        match self.ghost {
+            GhostStyle::Outline => {
+                self.set_color(piece.color);
+                for coord in piece.real_locations().iter() {
+                    self.outline_board_cell(coord.0 as i32, coord.1 as i32);
+                }
+            },
+            GhostStyle::Stipple => unsafe {
+                self.set_color(piece.color);
+                xlib::XSetStipple(self.display_ptr, self.gfx_context, self.stipple);
+                xlib::XSetFillStyle(self.display_ptr, self.gfx_context, xlib::FillStippled);
+                self.fill_board_piece(piece);
+                xlib::XSetFillStyle(self.display_ptr, self.gfx_context, xlib::FillSolid);
+            },
+            GhostStyle::Off => (),
+        }
+    }
+    
+    fn draw_piece(&mut self, piece: &Piece) {
+        self.set_color(piece.color);
+        self.fill_board_piece(piece);
+    }
+    
+    fn draw_hold(&mut self, piece: Option<&Piece>, available: bool) {
+        if let Some(piece) = piece {
+            self.set_color(piece.color);
+            self.draw_panel_piece(piece, self.layout.hold_y, available);
+        }
+    }
+    
+    fn draw_preview(&mut self, pieces: &[&Piece]) {
+        for (index, piece) in pieces.iter().enumerate() {
+            self.set_color(piece.color);
+            self.draw_panel_piece(piece, self.layout.preview_y + self.layout.preview_spacing*index as i32, true);
+        }
+    }
+    
+    fn draw_lock_bar(&mut self, remaining: f32) {
+        self.set_foreground(self.black);
+        let width = (self.layout.panel_width as f32 * remaining) as u32;
+        unsafe {
+            xlib::XFillRectangle(
+                self.display_ptr,
+                self.buffer,
+                self.gfx_context,
+                self.layout.panel_x, self.layout.lock_bar_y,
+                width, self.layout.lock_bar_height as u32,
+            );
+        }
+    }
+    
+    fn draw_hud(&mut self, lines: &[String]) {
+        self.set_foreground(self.black);
+        let hud_x = self.layout.board_x - self.layout.padding/2;
+        for (index, line) in lines.iter().enumerate() {
+            self.draw_text(hud_x, self.layout.hud_y + HUD_LINE*index as i32, line);
+        }
+    }
+    
+    fn draw_overlay(&mut self, lines: &[String]) {
+        if lines.is_empty() { return; }
+        
+        self.fill_white(
+            self.layout.board_x, self.layout.overlay_y,
+            self.layout.board_width as u32, self.layout.overlay_height as u32,
+        );
+        self.set_foreground(self.black);
+        for (index, line) in lines.iter().enumerate() {
+            self.draw_text(
+                self.layout.board_x + self.layout.cell/2,
+                self.layout.overlay_line_y(index as i32),
+                line,
+            );
+        }
+    }
+    
+    fn present(&mut self) {
+        self.redraw();
+    }
+}
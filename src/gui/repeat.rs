@@ -0,0 +1,218 @@
+use ::std::time::Duration;
+
+use super::bindings::Action;
+
+/// Default delay before a held shift starts repeating.
+pub const DEFAULT_DAS_MS: u64 = 167;
+/// Default time between repeated shifts.
+pub const DEFAULT_ARR_MS: u64 = 33;
+/// Default time between soft drop steps while soft drop is held.
+pub const DEFAULT_SOFT_DROP_MS: u64 = 25;
+
+/// A repeat count with no limit: move as far as the piece will go.
+pub const INSTANT: u32 = u32::MAX;
+
+/// A key being held, and how many repeats it has made so far.
+#[derive(Debug, Clone, PartialEq)]
+struct Held {
+    action: Action,
+    held: Duration,
+    repeats: u32,
+}
+
+impl Held {
+    fn new(action: Action) -> Held {
+        Held { action, held: Duration::from_millis(0), repeats: 0 }
+    }
+}
+
+/// Repeats held moves on the game's own clock instead of relying on the X
+/// server's autorepeat.
+///
+/// A held shift moves once when pressed, then again after the Delayed Auto
+/// Shift (DAS) and every Auto Repeat Rate (ARR) after that. Holding both
+/// directions shifts the way pressed last. A held soft drop steps down once
+/// every soft drop interval. An ARR or soft drop interval of zero moves the
+/// piece as far as it will go.
+#[derive(Debug, Clone)]
+pub struct AutoRepeat {
+    das: Duration,
+    arr: Duration,
+    soft_drop: Duration,
+    
+    /// Held shift directions, the one that repeats last.
+    shifts: Vec<Held>,
+    dropping: Option<Held>,
+}
+
+impl Default for AutoRepeat {
+    fn default() -> AutoRepeat {
+        AutoRepeat::new(DEFAULT_DAS_MS, DEFAULT_ARR_MS, DEFAULT_SOFT_DROP_MS)
+    }
+}
+
+impl AutoRepeat {
+    pub fn new(das_ms: u64, arr_ms: u64, soft_drop_ms: u64) -> AutoRepeat {
+        AutoRepeat {
+            das: Duration::from_millis(das_ms),
+            arr: Duration::from_millis(arr_ms),
+            soft_drop: Duration::from_millis(soft_drop_ms),
+            
+            shifts: Vec::new(),
+            dropping: None,
+        }
+    }
+    
+    /// Starts tracking a pressed key. Returns false if it was already held,
+    /// so the press shouldn't act again.
+    pub fn press(&mut self, action: Action) -> bool {
+        match action {
+            Action::MoveLeft | Action::MoveRight => {
+                if self.shifts.iter().any( |held| held.action == action ) { return false; }
+                self.shifts.push(Held::new(action));
+            },
+            Action::SoftDrop => {
+                if self.dropping.is_some() { return false; }
+                self.dropping = Some(Held::new(action));
+            },
+            _ => (),
+        }
+        true
+    }
+    
+    pub fn release(&mut self, action: Action) {
+        match action {
+            Action::MoveLeft | Action::MoveRight => {
+                let was_repeating = self.shifts.last().map( |held| held.action ) == Some(action);
+                self.shifts.retain( |held| held.action != action );
+                // The other direction takes over, charging its DAS afresh.
+                if was_repeating {
+                    if let Some(held) = self.shifts.last_mut() {
+                        *held = Held::new(held.action);
+                    }
+                }
+            },
+            Action::SoftDrop => self.dropping = None,
+            _ => (),
+        }
+    }
+    
+    /// Forgets every held key, for when releases can no longer be seen.
+    pub fn release_all(&mut self) {
+        self.shifts.clear();
+        self.dropping = None;
+    }
+    
+    /// Moves the held keys' clocks on by `elapsed`, returning each move that
+    /// has come due and how many times to make it, or `INSTANT`.
+    pub fn advance(&mut self, elapsed: Duration) -> Vec<(Action, u32)> {
+        let mut moves = Vec::new();
+        
+        if let Some(held) = self.shifts.last_mut() {
+            held.held += elapsed;
+            let due = if held.held < self.das {
+                0
+            } else {
+                AutoRepeat::repeats_due(held.held - self.das, self.arr).saturating_add(1)
+            };
+            AutoRepeat::catch_up(held, due, &mut moves);
+        }
+        if let Some(ref mut held) = self.dropping {
+            held.held += elapsed;
+            let due = AutoRepeat::repeats_due(held.held, self.soft_drop);
+            AutoRepeat::catch_up(held, due, &mut moves);
+        }
+        
+        moves
+    }
+    
+    /// Repeats made over `time` at one per `interval`, all at once for an
+    /// interval of zero.
+    fn repeats_due(time: Duration, interval: Duration) -> u32 {
+        if interval.as_nanos() == 0 {
+            INSTANT
+        } else {
+            (time.as_nanos() / interval.as_nanos()) as u32
+        }
+    }
+    
+    fn catch_up(held: &mut Held, due: u32, moves: &mut Vec<(Action, u32)>) {
+        if due == INSTANT {
+            // Keep pushing, in case the piece has moved off the wall.
+            moves.push((held.action, INSTANT));
+        } else if due > held.repeats {
+            moves.push((held.action, due - held.repeats));
+        }
+        held.repeats = due;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::std::time::Duration;
+    use super::super::bindings::Action::*;
+    use super::{AutoRepeat, INSTANT};
+    
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+    
+    #[test]
+    fn shift_waits_for_das_then_repeats() {
+        let mut repeat = AutoRepeat::new(100, 20, 10);
+        assert!(repeat.press(MoveLeft));
+        assert!(!repeat.press(MoveLeft));
+        
+        assert!(repeat.advance(ms(99)).is_empty());
+        assert_eq!(vec![(MoveLeft, 1)], repeat.advance(ms(1)));
+        assert!(repeat.advance(ms(19)).is_empty());
+        assert_eq!(vec![(MoveLeft, 3)], repeat.advance(ms(41)));
+        
+        repeat.release(MoveLeft);
+        assert!(repeat.advance(ms(100)).is_empty());
+        assert!(repeat.press(MoveLeft));
+    }
+    
+    #[test]
+    fn last_direction_wins() {
+        let mut repeat = AutoRepeat::new(100, 20, 10);
+        repeat.press(MoveLeft);
+        repeat.advance(ms(150));
+        repeat.press(MoveRight);
+        assert!(repeat.advance(ms(50)).is_empty());
+        assert_eq!(vec![(MoveRight, 1)], repeat.advance(ms(50)));
+        
+        repeat.release(MoveRight);
+        assert!(repeat.advance(ms(99)).is_empty());
+        assert_eq!(vec![(MoveLeft, 1)], repeat.advance(ms(1)));
+        
+        // Letting go of the direction that isn't repeating changes nothing.
+        repeat.press(MoveRight);
+        repeat.release(MoveLeft);
+        assert_eq!(vec![(MoveRight, 1)], repeat.advance(ms(100)));
+    }
+    
+    #[test]
+    fn soft_drop_steps_without_delay() {
+        let mut repeat = AutoRepeat::new(100, 20, 10);
+        repeat.press(SoftDrop);
+        repeat.press(HardDrop);
+        assert_eq!(vec![(SoftDrop, 2)], repeat.advance(ms(25)));
+        
+        repeat.press(MoveRight);
+        assert_eq!(vec![(SoftDrop, 10)], repeat.advance(ms(99)));
+        
+        repeat.release_all();
+        assert!(repeat.advance(ms(100)).is_empty());
+    }
+    
+    #[test]
+    fn zero_intervals_are_instant() {
+        let mut repeat = AutoRepeat::new(50, 0, 0);
+        repeat.press(MoveRight);
+        repeat.press(SoftDrop);
+        assert_eq!(vec![(SoftDrop, INSTANT)], repeat.advance(ms(10)));
+        assert_eq!(vec![(MoveRight, INSTANT), (SoftDrop, INSTANT)], repeat.advance(ms(40)));
+        assert_eq!(vec![(MoveRight, INSTANT), (SoftDrop, INSTANT)], repeat.advance(ms(16)));
+    }
+}
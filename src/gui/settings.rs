@@ -0,0 +1,153 @@
+use ::std::path::{Path, PathBuf};
+
+use render::animation::{LineClearStyle, DEFAULT_LINE_CLEAR_MS};
+use render::layout::DEFAULT_CELL;
+pub use super::bindings::Bindings;
+pub use super::renderer::GhostStyle;
+use super::config;
+use super::repeat::{DEFAULT_DAS_MS, DEFAULT_ARR_MS, DEFAULT_SOFT_DROP_MS};
+
+/// Frontend options that don't affect the game itself.
+pub struct Settings {
+    /// Hidden rows of the vanish zone to show above the playfield.
+    pub peek_rows: usize,
+    /// How cleared rows are animated.
+    pub line_clear: LineClearStyle,
+    /// How long the line clear animation lasts, holding back the next piece.
+    pub line_clear_ms: u64,
+    /// How the projection of the falling piece is drawn.
+    pub ghost: GhostStyle,
+    /// Smallest cell size, in pixels, the window may be shrunk to. The
+    /// window starts at the default cell size and scales with it.
+    pub min_cell: i32,
+    /// Which keys trigger which actions.
+    pub bindings: Bindings,
+    /// Delayed Auto Shift: how long a shift is held before it repeats.
+    pub das_ms: u64,
+    /// Auto Repeat Rate: time between repeated shifts, zero to go straight
+    /// to the wall.
+    pub arr_ms: u64,
+    /// Time between steps of a held soft drop, zero to go straight to the
+    /// floor.
+    pub soft_drop_ms: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            peek_rows: 0,
+            line_clear: LineClearStyle::Flash,
+            line_clear_ms: DEFAULT_LINE_CLEAR_MS,
+            ghost: GhostStyle::Outline,
+            min_cell: DEFAULT_CELL/2,
+            bindings: Bindings::default(),
+            das_ms: DEFAULT_DAS_MS,
+            arr_ms: DEFAULT_ARR_MS,
+            soft_drop_ms: DEFAULT_SOFT_DROP_MS,
+        }
+    }
+}
+
+impl Settings {
+    /// The defaults, changed by each `name = value` line of `text`. Keys
+    /// are bound in a file of their own.
+    ///
+    /// ```text
+    /// line_clear = dissolve   # flash, dissolve or off
+    /// line_clear_ms = 200
+    /// ghost = stipple         # outline, stipple or off
+    /// das_ms = 120
+    /// arr_ms = 0              # straight to the wall
+    /// soft_drop_ms = 10
+    /// ```
+    pub fn parse(text: &str) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        
+        for (line, name, value) in config::entries(text)? {
+            let parsed = match name {
+                "line_clear" => choice(value, &[
+                    ("flash", LineClearStyle::Flash),
+                    ("dissolve", LineClearStyle::Dissolve),
+                    ("off", LineClearStyle::Off),
+                ]).map( |style| settings.line_clear = style ),
+                "line_clear_ms" => millis(value).map( |ms| settings.line_clear_ms = ms ),
+                "ghost" => choice(value, &[
+                    ("outline", GhostStyle::Outline),
+                    ("stipple", GhostStyle::Stipple),
+                    ("off", GhostStyle::Off),
+                ]).map( |style| settings.ghost = style ),
+                "das_ms" => millis(value).map( |ms| settings.das_ms = ms ),
+                "arr_ms" => millis(value).map( |ms| settings.arr_ms = ms ),
+                "soft_drop_ms" => millis(value).map( |ms| settings.soft_drop_ms = ms ),
+                _ => Err(format!("unknown setting `{}`", name)),
+            };
+            parsed.map_err( |error| format!("line {}: {}", line, error) )?;
+        }
+        
+        Ok(settings)
+    }
+    
+    pub fn load(path: &Path) -> Result<Settings, String> {
+        Settings::parse(&config::read(path)?)
+    }
+    
+    /// Where the settings file lives: `tetroids/settings.conf`, next to the
+    /// bindings file.
+    pub fn config_path() -> Option<PathBuf> {
+        config::path("settings.conf")
+    }
+}
+
+/// The value among `choices` named `value`.
+fn choice<T: Copy>(value: &str, choices: &[(&str, T)]) -> Result<T, String> {
+    match choices.iter().find( |&&(name, _)| name == value ) {
+        Some(&(_, choice)) => Ok(choice),
+        None => {
+            let names = choices.iter().map( |&(name, _)| name ).collect::<Vec<_>>();
+            Err(format!("expected {}, not `{}`", names.join(", "), value))
+        },
+    }
+}
+
+fn millis(value: &str) -> Result<u64, String> {
+    value.parse().map_err( |_| format!("expected milliseconds, not `{}`", value) )
+}
+
+#[cfg(test)]
+mod tests {
+    use render::animation::LineClearStyle;
+    use super::{GhostStyle, Settings};
+    
+    #[test]
+    fn file_overrides_defaults() {
+        let settings = Settings::parse("line_clear = off\nline_clear_ms = 120").unwrap();
+        assert_eq!(LineClearStyle::Off, settings.line_clear);
+        assert_eq!(120, settings.line_clear_ms);
+        
+        let settings = Settings::parse("ghost = off").unwrap();
+        assert_eq!(GhostStyle::Off, settings.ghost);
+        
+        let settings = Settings::parse("das_ms = 120\narr_ms = 0\nsoft_drop_ms = 10").unwrap();
+        assert_eq!((120, 0, 10), (settings.das_ms, settings.arr_ms, settings.soft_drop_ms));
+        
+        let settings = Settings::parse("# nothing set").unwrap();
+        assert_eq!(LineClearStyle::Flash, settings.line_clear);
+        assert_eq!(GhostStyle::Outline, settings.ghost);
+    }
+    
+    #[test]
+    fn bad_settings() {
+        assert_eq!(
+            Some("line 1: expected flash, dissolve, off, not `fade`".to_string()),
+            Settings::parse("line_clear = fade").err(),
+        );
+        assert_eq!(
+            Some("line 2: expected milliseconds, not `-5`".to_string()),
+            Settings::parse("\nline_clear_ms = -5").err(),
+        );
+        assert_eq!(
+            Some("line 1: unknown setting `speed`".to_string()),
+            Settings::parse("speed = 9").err(),
+        );
+    }
+}
@@ -2,20 +2,50 @@ use super::xlib;
 use super::game::color;
 use ::std::os::raw::{c_ushort, c_char, c_ulong};
 
+/// Widens an 8-bit channel to the 16 bits `XColor` uses, so 255 maps to
+/// 65535 rather than staying near black.
+const fn scale(channel: u8) -> c_ushort {
+    channel as c_ushort * 257
+}
+
+/// The `XColor` to allocate for `color`.
+pub const fn of(color: color::Color) -> xlib::XColor {
+    xlib::XColor {
+        red:   scale(color.0),
+        green: scale(color.1),
+        blue:  scale(color.2),
+        
+        pixel: 0 as c_ulong,
+        flags: xlib::DoRed | xlib::DoGreen | xlib::DoBlue,
+        pad: 0 as c_char,
+    }
+}
+
 macro_rules! xcolor {
     ($($x:ident),*) => (
         $(
-            const $x: xlib::XColor = xlib::XColor {
-                red:   color::named::$x.0 as c_ushort,
-                green: color::named::$x.1 as c_ushort,
-                blue:  color::named::$x.2 as c_ushort,
-                
-                pixel: 0 as c_ulong,
-                flags: xlib::DoRed | xlib::DoGreen | xlib::DoBlue,
-                pad: 0 as c_char,
-            };
+            pub const $x: xlib::XColor = of(color::named::$x);
         )*
+        
+        /// Every named color, to allocate up front.
+        pub const PALETTE: &[(color::Color, xlib::XColor)] = &[
+            $( (color::named::$x, $x) ),*
+        ];
     );
 }
 
-xcolor!(YELLOW, GREEN, CYAN, BLUE, PURPLE, RED, ORANGE);
+xcolor!(YELLOW, GREEN, CYAN, BLUE, PURPLE, RED, ORANGE, GRAY);
+
+#[cfg(test)]
+mod tests {
+    use super::super::game::color::Color;
+    
+    #[test]
+    fn channels_fill_sixteen_bits() {
+        assert_eq!(65535, super::YELLOW.red);
+        assert_eq!(65535, super::YELLOW.green);
+        assert_eq!(0, super::YELLOW.blue);
+        assert_eq!(127*257, super::GRAY.red);
+        assert_eq!(0x1212, super::of(Color(0x12, 0, 0)).red);
+    }
+}
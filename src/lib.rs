@@ -0,0 +1,13 @@
+//! A Tetris engine, with an optional X11 frontend behind the `gui` feature.
+
+// Failed moves, rotations and holds have nothing to report beyond failing.
+#![allow(clippy::result_unit_err)]
+
+#[cfg(feature = "gui")]
+extern crate x11;
+extern crate rand;
+
+pub mod game;
+pub mod render;
+#[cfg(feature = "gui")]
+pub mod gui;
@@ -1,11 +1,30 @@
-extern crate x11;
-extern crate rand;
+extern crate tetroids;
 
-mod game;
-mod gui;
+use tetroids::{game, gui};
+use tetroids::gui::settings::{Bindings, Settings};
 
 fn main() {
-    let mut game = game::Game::new();
-    let mut interface = gui::GUI::new();
+    let mut game = match std::env::args().nth(1) {
+        Some(seed) => game::Game::with_seed(
+            seed.parse().expect("Seed must be an unsigned 64-bit integer.")
+        ),
+        None => game::Game::new(),
+    };
+    
+    let mut settings = Settings::default();
+    if let Some(path) = Settings::config_path().filter( |path| path.exists() ) {
+        match Settings::load(&path) {
+            Ok(loaded) => settings = loaded,
+            Err(error) => println!("Ignoring {}: {}", path.display(), error),
+        }
+    }
+    if let Some(path) = Bindings::config_path().filter( |path| path.exists() ) {
+        match Bindings::load(&path) {
+            Ok(bindings) => settings.bindings = bindings,
+            Err(error) => println!("Ignoring {}: {}", path.display(), error),
+        }
+    }
+    
+    let mut interface = gui::GUI::new(&game, settings);
     interface.play(&mut game);
 }
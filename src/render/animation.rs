@@ -0,0 +1,68 @@
+/// How cleared rows leave the board.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineClearStyle {
+    /// The rows blink on and off.
+    Flash,
+    /// The rows vanish from the middle column outwards.
+    Dissolve,
+    /// No animation; the next piece spawns immediately.
+    Off,
+}
+
+pub const DEFAULT_LINE_CLEAR_MS: u64 = 300;
+const FLASHES: f32 = 3.0;
+
+/// Where row `y` of the board was before the `cleared` rows, lowest first,
+/// were removed from under it.
+pub fn row_before_clear(cleared: &[usize], y: usize) -> usize {
+    cleared.iter().fold(y, |row, &cleared| if cleared <= row { row + 1 } else { row })
+}
+
+/// Whether the cleared cell in column `x` of a board `width` wide is still
+/// drawn `progress` of the way through the animation.
+pub fn shows_cell(style: LineClearStyle, progress: f32, x: usize, width: usize) -> bool {
+    match style {
+        LineClearStyle::Flash => ((progress * 2.0 * FLASHES) as u32).is_multiple_of(2),
+        LineClearStyle::Dissolve => {
+            // Doubled so the middle of even and odd widths is exact.
+            let distance = (2*x as i32 + 1 - width as i32).abs() as f32;
+            distance / width as f32 >= progress
+        },
+        LineClearStyle::Off => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{row_before_clear, shows_cell};
+    use super::LineClearStyle::*;
+    
+    #[test]
+    fn rows_shift_down() {
+        let cleared = [1, 3];
+        assert_eq!(0, row_before_clear(&cleared, 0));
+        assert_eq!(2, row_before_clear(&cleared, 1));
+        assert_eq!(4, row_before_clear(&cleared, 2));
+        assert_eq!(7, row_before_clear(&cleared, 5));
+        assert_eq!(5, row_before_clear(&[], 5));
+    }
+    
+    #[test]
+    fn flash_blinks() {
+        let frames = (0..6)
+            .map( |frame| shows_cell(Flash, (frame as f32 + 0.5) / 6.0, 0, 10) )
+            .collect::<Vec<_>>();
+        assert_eq!(vec![true, false, true, false, true, false], frames);
+    }
+    
+    #[test]
+    fn dissolve_from_middle() {
+        assert!((0..10).all( |x| shows_cell(Dissolve, 0.0, x, 10) ));
+        assert!(!shows_cell(Dissolve, 0.5, 4, 10));
+        assert!(!shows_cell(Dissolve, 0.5, 5, 10));
+        assert!(shows_cell(Dissolve, 0.5, 0, 10));
+        assert!(shows_cell(Dissolve, 0.5, 9, 10));
+        assert!((0..10).all( |x| !shows_cell(Dissolve, 1.0, x, 10) ));
+        assert!(!shows_cell(Off, 0.0, 0, 10));
+    }
+}
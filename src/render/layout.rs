@@ -0,0 +1,217 @@
+use ::std::cmp;
+
+use game;
+
+/// Cell size, in pixels, of the window's initial layout.
+pub const DEFAULT_CELL: i32 = 12;
+/// Smallest cell that still leaves a visible block inside its border.
+pub const MIN_CELL: i32 = 4;
+/// Text doesn't scale, so HUD lines are always this far apart.
+pub const HUD_LINE: i32 = 14;
+const HUD_LINES: i32 = 3;
+/// Spacing of the overlay's text lines, which don't scale either.
+pub const OVERLAY_LINE: i32 = 18;
+/// Most lines the overlay shows, on the game over screen.
+const OVERLAY_LINES: i32 = 4;
+
+/// Pixel positions of everything drawn, derived from the size of the board,
+/// the length of the preview queue, how far to peek into the vanish zone
+/// and the size of a cell. Everything but the HUD text scales with the cell.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub rows: i32,
+    pub peek_rows: i32,
+    
+    pub cell: i32,
+    pub cell_fill: i32,
+    pub padding: i32,
+    pub preview_cell: i32,
+    pub preview_spacing: i32,
+    pub panel_width: i32,
+    pub lock_bar_height: i32,
+    
+    pub board_x: i32,
+    pub board_y: i32,
+    pub board_width: i32,
+    pub board_height: i32,
+    
+    pub panel_x: i32,
+    pub hold_y: i32,
+    pub preview_y: i32,
+    pub preview_height: i32,
+    pub lock_bar_y: i32,
+    
+    /// The box overlay text is drawn in, centered on the board.
+    pub overlay_y: i32,
+    pub overlay_height: i32,
+    
+    pub hud_y: i32,
+    
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Layout {
+    pub fn new(game: &game::Game, peek_rows: usize) -> Layout {
+        Layout::with_cell(game, peek_rows, DEFAULT_CELL, 0, 0)
+    }
+    
+    /// The largest layout that fits in a `width` by `height` window, centered
+    /// in it. Cells never shrink below `MIN_CELL`, so the result may overflow
+    /// a very small window.
+    pub fn fit(game: &game::Game, peek_rows: usize, width: i32, height: i32) -> Layout {
+        let natural = Layout::new(game, peek_rows);
+        let text_height = HUD_LINES*HUD_LINE;
+        
+        // The scaled parts of the layout grow in proportion to the cell.
+        let cell = cmp::min(
+            DEFAULT_CELL*width / natural.width,
+            DEFAULT_CELL*(height - text_height) / (natural.height - text_height),
+        );
+        let cell = cmp::max(cell, MIN_CELL);
+        
+        let sized = Layout::with_cell(game, peek_rows, cell, 0, 0);
+        Layout::with_cell(
+            game, peek_rows, cell,
+            cmp::max(0, (width - sized.width)/2),
+            cmp::max(0, (height - sized.height)/2),
+        )
+    }
+    
+    /// The layout with `cell` pixel cells, whose top left corner is at `left`,
+    /// `top`.
+    pub fn with_cell(game: &game::Game, peek_rows: usize, cell: i32, left: i32, top: i32) -> Layout {
+        let cell = cmp::max(cell, MIN_CELL);
+        let padding = 2*cell;
+        let preview_cell = 2*cell/3;
+        let preview_spacing = 3*preview_cell;
+        let panel_width = 4*preview_cell;
+        let lock_bar_height = cmp::max(1, cell/3);
+        
+        let peek_rows = cmp::min(peek_rows, game.buffer_height()) as i32;
+        let board_width  = cell*game.board_width()  as i32;
+        let board_height = cell*game.board_height() as i32;
+        let peek_height  = cell*peek_rows;
+        
+        let preview_y = preview_spacing + 2*padding;
+        let preview_height = preview_spacing*game.get_preview().count() as i32;
+        let content_height = cmp::max(peek_height + board_height, preview_height + preview_y);
+        let overlay_height = cmp::min(board_height, OVERLAY_LINES*OVERLAY_LINE + cell);
+        
+        Layout {
+            rows: game.board_height() as i32,
+            peek_rows,
+            
+            cell,
+            cell_fill: cell - 2,
+            padding,
+            preview_cell,
+            preview_spacing,
+            panel_width,
+            lock_bar_height,
+            
+            board_x: left + padding,
+            board_y: top + padding + peek_height,
+            board_width,
+            board_height,
+            
+            panel_x: left + board_width + 2*padding,
+            hold_y: top + padding,
+            preview_y: top + preview_y,
+            preview_height,
+            lock_bar_y: top + padding + content_height - lock_bar_height,
+            
+            overlay_y: top + padding + peek_height + (board_height - overlay_height)/2,
+            overlay_height,
+            
+            hud_y: top + content_height + 2*padding + HUD_LINE,
+            
+            width: board_width + 3*padding + panel_width,
+            height: content_height + 3*padding + HUD_LINES*HUD_LINE,
+        }
+    }
+    
+    /// Left edge of the filled part of the board cell in column `x`.
+    pub fn cell_x(&self, x: i32) -> i32 {
+        self.board_x + self.cell*x + 1
+    }
+    
+    /// Top edge of the filled part of the board cell in row `y`, counting
+    /// rows up from the bottom of the board. Peeked rows of the vanish zone
+    /// sit above `board_y`.
+    pub fn cell_y(&self, y: i32) -> i32 {
+        self.board_y + self.cell*(self.rows - 1 - y) + 1
+    }
+    
+    /// Baseline of line `index` of the overlay text.
+    pub fn overlay_line_y(&self, index: i32) -> i32 {
+        // The bottom of the line is left for descenders.
+        self.overlay_y + self.cell/2 + OVERLAY_LINE*(index + 1) - 4
+    }
+    
+    /// Whether row `y` is drawn: the visible rows plus any peeked rows.
+    pub fn shows_row(&self, y: i32) -> bool {
+        y < self.rows + self.peek_rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use game::Game;
+    use super::*;
+    
+    #[test]
+    fn cells_count_up_from_bottom() {
+        let layout = Layout::new(&Game::new(), 0);
+        assert_eq!(2*DEFAULT_CELL + 1, layout.cell_x(0));
+        assert_eq!(layout.board_y + layout.board_height - DEFAULT_CELL + 1, layout.cell_y(0));
+        assert_eq!(layout.board_y + 1, layout.cell_y(19));
+        assert!(layout.shows_row(19));
+        assert!(!layout.shows_row(20));
+    }
+    
+    #[test]
+    fn peek_rows_capped_by_buffer() {
+        let mut game = Game::new();
+        game.set_buffer_height(2);
+        let layout = Layout::new(&game, 5);
+        assert_eq!(2, layout.peek_rows);
+        assert_eq!(layout.padding + 2*DEFAULT_CELL, layout.board_y);
+        assert_eq!(layout.padding + 1, layout.cell_y(21));
+        assert!(!layout.shows_row(22));
+    }
+    
+    #[test]
+    fn fit_scales_and_centers() {
+        let game = Game::new();
+        let natural = Layout::new(&game, 0);
+        assert_eq!(natural, Layout::fit(&game, 0, natural.width, natural.height));
+        
+        let text_height = HUD_LINES*HUD_LINE;
+        let tall = Layout::fit(&game, 0, 2*natural.width, 4*natural.height);
+        assert_eq!(2*DEFAULT_CELL, tall.cell);
+        assert_eq!(2*natural.width, tall.width);
+        assert_eq!(2*(natural.height - text_height) + text_height, tall.height);
+        assert_eq!(tall.padding, tall.board_x);
+        assert_eq!((4*natural.height - tall.height)/2 + tall.padding, tall.board_y);
+        
+        let wide = Layout::fit(&game, 0, 3*natural.width, natural.height);
+        assert_eq!(DEFAULT_CELL, wide.cell);
+        assert_eq!(natural.width + natural.padding, wide.board_x);
+        
+        let tiny = Layout::fit(&game, 0, 10, 10);
+        assert_eq!(MIN_CELL, tiny.cell);
+        assert_eq!(tiny.padding, tiny.board_x);
+    }
+    
+    #[test]
+    fn overlay_stays_on_board() {
+        let game = Game::new();
+        for &cell in [MIN_CELL, DEFAULT_CELL/2, DEFAULT_CELL, 3*DEFAULT_CELL].iter() {
+            let layout = Layout::with_cell(&game, 2, cell, 0, 0);
+            assert!(layout.overlay_y >= layout.board_y, "cell {}", cell);
+            assert!(layout.overlay_y + layout.overlay_height <= layout.board_y + layout.board_height, "cell {}", cell);
+            assert!(layout.overlay_line_y(OVERLAY_LINES - 1) < layout.overlay_y + layout.overlay_height, "cell {}", cell);
+        }
+    }
+}
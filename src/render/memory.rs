@@ -0,0 +1,77 @@
+use game::Piece;
+use game::color::Color;
+use game::piece::Kind;
+use super::Renderer;
+
+/// A `Renderer` that keeps the last frame in memory, for tests and tools
+/// that want to inspect what would be drawn.
+#[derive(Default)]
+pub struct MemoryRenderer {
+    /// Frames started so far.
+    pub frames: usize,
+    pub cells: Vec<(usize, usize, Color)>,
+    pub ghost: Option<Piece>,
+    pub piece: Option<Piece>,
+    /// The held piece, and whether hold is available.
+    pub hold: Option<(Kind, bool)>,
+    pub preview: Vec<Kind>,
+    pub lock_bar: Option<f32>,
+    pub hud: Vec<String>,
+    pub overlay: Vec<String>,
+}
+
+impl MemoryRenderer {
+    pub fn new() -> MemoryRenderer {
+        Default::default()
+    }
+    
+    /// Color of the stack cell drawn at `x`, `y`, if any.
+    pub fn cell(&self, x: usize, y: usize) -> Option<Color> {
+        self.cells
+            .iter()
+            .rev()
+            .find( |&&(cell_x, cell_y, _)| (cell_x, cell_y) == (x, y) )
+            .map( |&(_, _, color)| color )
+    }
+}
+
+impl Renderer for MemoryRenderer {
+    fn clear(&mut self) {
+        *self = MemoryRenderer {
+            frames: self.frames + 1,
+            ..Default::default()
+        };
+    }
+    
+    fn draw_cell(&mut self, x: usize, y: usize, color: Color) {
+        self.cells.push((x, y, color));
+    }
+    
+    fn draw_ghost(&mut self, piece: &Piece) {
+        self.ghost = Some(piece.clone());
+    }
+    
+    fn draw_piece(&mut self, piece: &Piece) {
+        self.piece = Some(piece.clone());
+    }
+    
+    fn draw_hold(&mut self, piece: Option<&Piece>, available: bool) {
+        self.hold = piece.map( |piece| (piece.kind, available) );
+    }
+    
+    fn draw_preview(&mut self, pieces: &[&Piece]) {
+        self.preview = pieces.iter().map( |piece| piece.kind ).collect();
+    }
+    
+    fn draw_lock_bar(&mut self, remaining: f32) {
+        self.lock_bar = Some(remaining);
+    }
+    
+    fn draw_hud(&mut self, lines: &[String]) {
+        self.hud = lines.to_vec();
+    }
+    
+    fn draw_overlay(&mut self, lines: &[String]) {
+        self.overlay = lines.to_vec();
+    }
+}
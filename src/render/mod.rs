@@ -0,0 +1,230 @@
+//! Drawing a game, independent of where it is drawn to.
+
+pub mod animation;
+pub mod layout;
+pub mod memory;
+
+use game::{Game, GameState, Piece};
+use game::color::Color;
+use self::animation::LineClearStyle;
+
+/// A drawing backend. `draw_game` calls these once per frame, starting with
+/// `clear`, in the order declared here. Board rows count up from the bottom
+/// and include the vanish zone; backends skip rows they have no room for.
+pub trait Renderer {
+    /// Starts a new frame, erasing the last one.
+    fn clear(&mut self);
+    /// A cell of the stack, or of a row being cleared.
+    fn draw_cell(&mut self, x: usize, y: usize, color: Color);
+    /// Where the falling piece would land.
+    fn draw_ghost(&mut self, piece: &Piece);
+    /// The falling piece.
+    fn draw_piece(&mut self, piece: &Piece);
+    /// The hold box. `available` is false once hold has been used for the
+    /// current piece.
+    fn draw_hold(&mut self, piece: Option<&Piece>, available: bool);
+    /// The preview queue, next piece first.
+    fn draw_preview(&mut self, pieces: &[&Piece]);
+    /// Fraction of the lock delay left while the piece rests on the stack.
+    fn draw_lock_bar(&mut self, remaining: f32);
+    /// Score, level and the last clear, one entry per line.
+    fn draw_hud(&mut self, lines: &[String]);
+    /// Text shown over the board outside normal play.
+    fn draw_overlay(&mut self, lines: &[String]);
+    /// Shows the finished frame, for backends that draw off screen.
+    fn present(&mut self) {}
+}
+
+/// Names of the keys the overlay tells the player about, as they should
+/// be shown. A hint is left out when its name is empty.
+#[derive(Debug, Clone, Default)]
+pub struct KeyHints {
+    pub pause: String,
+    pub restart: String,
+}
+
+/// Draws a whole frame of `game`. `clear_label` names the last scoring
+/// clear, for the HUD and the line clear overlay.
+pub fn draw_game<R: Renderer>(
+    renderer: &mut R,
+    game: &Game,
+    line_clear: LineClearStyle,
+    clear_label: &str,
+    keys: &KeyHints,
+) {
+    renderer.clear();
+    
+    // open the cleared rows back up while they animate
+    let animation = game.get_line_clear_progress().and_then(
+        |progress| game.get_line_clear().map( |clear| (progress, clear) )
+    );
+    let cleared: &[usize] = match animation {
+        Some((_, clear)) => &clear.rows,
+        None => &[],
+    };
+    for (x, y, square) in game.board_iter_with_index() {
+        if let Some(square) = square {
+            renderer.draw_cell(x, animation::row_before_clear(cleared, y), square.0);
+        }
+    }
+    if let Some((progress, clear)) = animation {
+        for &(x, y, square) in clear.cells.iter() {
+            if animation::shows_cell(line_clear, progress, x, game.board_width()) {
+                renderer.draw_cell(x, y, square.0);
+            }
+        }
+    }
+    
+    if let Some(projection) = game.get_projection() {
+        renderer.draw_ghost(projection);
+    }
+    if let Some(cursor) = game.get_cursor() {
+        renderer.draw_piece(cursor);
+    }
+    renderer.draw_hold(game.get_hold(), game.can_hold());
+    renderer.draw_preview(&game.get_preview().collect::<Vec<&Piece>>());
+    
+    if let Some(lock_timer) = game.get_lock_timer() {
+        let lock_delay = game.get_lock_delay();
+        let remaining = lock_delay.checked_sub(lock_timer).unwrap_or_default();
+        let fraction = if lock_delay.as_millis() == 0 { 0.0 } else {
+            remaining.as_secs_f32() / lock_delay.as_secs_f32()
+        };
+        renderer.draw_lock_bar(fraction);
+    }
+    
+    renderer.draw_hud(&[
+        format!("Score {}", game.get_score()),
+        format!("Level {}  Lines {}", game.get_level(), game.get_lines()),
+        clear_label.to_string(),
+    ]);
+    renderer.draw_overlay(&overlay_lines(game, clear_label, keys));
+    renderer.present();
+}
+
+/// Text shown over the board in each state other than `Playing`.
+pub fn overlay_lines(game: &Game, clear_label: &str, keys: &KeyHints) -> Vec<String> {
+    let hint = |key: &str, what: &str| {
+        if key.is_empty() { None } else { Some(format!("{}: {}", key, what)) }
+    };
+    match game.get_state() {
+        GameState::Playing => vec![],
+        GameState::Countdown => vec![
+            "READY".to_string(),
+            game.get_countdown().to_string(),
+        ],
+        GameState::Paused => {
+            let mut lines = vec!["PAUSED".to_string()];
+            lines.extend(hint(&keys.pause, "resume"));
+            lines.extend(hint(&keys.restart, "restart"));
+            lines
+        },
+        GameState::LineClearAnimation => {
+            if clear_label.is_empty() {
+                vec![]
+            } else {
+                vec![clear_label.to_string()]
+            }
+        },
+        GameState::Over => {
+            let mut lines = vec![
+                "GAME OVER".to_string(),
+                format!("Score: {}", game.get_score()),
+            ];
+            if let Some(seed) = game.seed() {
+                lines.push(format!("Seed: {}", seed));
+            }
+            lines.extend(hint(&keys.restart, "restart"));
+            lines
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::std::time::Duration;
+    use game::{Game, GameState, Movement};
+    use game::color::named;
+    use game::piece::{template, Kind};
+    use game::randomizer::Sequence;
+    use super::{draw_game, overlay_lines, KeyHints};
+    use super::animation::LineClearStyle;
+    use super::memory::MemoryRenderer;
+    
+    fn started(kinds: Vec<Kind>) -> Game {
+        let mut game = Game::with_randomizer(Box::new(Sequence::new(kinds)));
+        game.advance(Duration::from_millis(::game::DEFAULT_COUNTDOWN_MS));
+        game
+    }
+    
+    #[test]
+    fn draws_countdown() {
+        let game = Game::with_randomizer(Box::new(Sequence::new(vec![Kind::T])));
+        let mut renderer = MemoryRenderer::new();
+        draw_game(&mut renderer, &game, LineClearStyle::Flash, "", &KeyHints::default());
+        
+        assert_eq!(1, renderer.frames);
+        assert!(renderer.piece.is_none());
+        assert_eq!(vec!["READY", "3"], renderer.overlay);
+        assert_eq!(5, renderer.preview.len());
+    }
+    
+    #[test]
+    fn draws_pieces_and_panels() {
+        let mut game = started(vec![Kind::T, Kind::O, Kind::I]);
+        game.hold_cursor().unwrap();
+        let mut renderer = MemoryRenderer::new();
+        draw_game(&mut renderer, &game, LineClearStyle::Flash, "TETRIS", &KeyHints::default());
+        
+        assert_eq!(Kind::O, renderer.piece.as_ref().unwrap().kind);
+        assert_eq!(0, renderer.ghost.as_ref().unwrap().coord.1);
+        assert_eq!(Some((Kind::T, false)), renderer.hold);
+        assert_eq!(Kind::I, renderer.preview[0]);
+        assert!(renderer.cells.is_empty());
+        assert!(renderer.lock_bar.is_none());
+        assert_eq!(vec!["Score 0", "Level 1  Lines 0", "TETRIS"], renderer.hud);
+        assert!(renderer.overlay.is_empty());
+        
+        for _ in 0..40 { game.try_move_cursor(Movement::SoftDrop).ok(); }
+        draw_game(&mut renderer, &game, LineClearStyle::Flash, "", &KeyHints::default());
+        assert_eq!(2, renderer.frames);
+        assert_eq!(Some(1.0), renderer.lock_bar);
+        assert_eq!(renderer.ghost.unwrap().coord, renderer.piece.unwrap().coord);
+    }
+    
+    #[test]
+    fn overlay_names_bound_keys() {
+        let mut game = started(vec![Kind::T]);
+        game.pause().unwrap();
+        let keys = KeyHints { pause: "p/Escape".to_string(), restart: String::new() };
+        assert_eq!(vec!["PAUSED", "p/Escape: resume"], overlay_lines(&game, "", &keys));
+        
+        game.resume().unwrap();
+        game.add_garbage(41, 0);
+        let keys = KeyHints { pause: String::new(), restart: "r".to_string() };
+        assert_eq!(vec!["GAME OVER", "Score: 0", "r: restart"], overlay_lines(&game, "", &keys));
+    }
+    
+    #[test]
+    fn draws_cleared_rows_in_place() {
+        let mut game = started(vec![Kind::I]);
+        game.set_board_size(4, 8);
+        game.restart();
+        game.advance(Duration::from_millis(::game::DEFAULT_COUNTDOWN_MS));
+        game.add_garbage(1, 0);
+        game.try_move_cursor(Movement::HardDrop).unwrap();
+        assert_eq!(GameState::LineClearAnimation, game.get_state());
+        
+        let mut renderer = MemoryRenderer::new();
+        draw_game(&mut renderer, &game, LineClearStyle::Flash, "SINGLE", &KeyHints::default());
+        assert_eq!(None, renderer.cell(0, 0));
+        assert_eq!(Some(named::GRAY), renderer.cell(1, 0));
+        assert_eq!(Some(template::I.color), renderer.cell(0, 1));
+        assert_eq!(3 + 4, renderer.cells.len());
+        assert_eq!(vec!["SINGLE"], renderer.overlay);
+        
+        draw_game(&mut renderer, &game, LineClearStyle::Off, "SINGLE", &KeyHints::default());
+        assert_eq!(3, renderer.cells.len());
+        assert_eq!(None, renderer.cell(0, 1));
+    }
+}